@@ -0,0 +1,181 @@
+use crate::WorkloadRecorder;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Server-wide settings shared by every session of the MCP server.
+///
+/// The defaults preserve the behavior of a server started without any flags.
+#[derive(Debug, Clone, Default)]
+pub struct PgMcpConfig {
+    /// Maximum tool calls per second, per registered conn_id (or per session
+    /// for calls without one). `None` disables rate limiting.
+    pub rate_limit: Option<u32>,
+    /// Reject every schema-modifying tool (create/drop table, index, schema,
+    /// type, ...) while still allowing reads and data writes.
+    pub disable_ddl: bool,
+    /// Run every insert, update and delete in its own transaction with these
+    /// deadlines. `None` runs writes in autocommit mode.
+    pub wrap_writes_in_tx: Option<WriteTxConfig>,
+    /// Reject UPDATE and DELETE statements that reference other tables than
+    /// the one they write to (FROM, USING or JOIN).
+    pub single_table_writes: bool,
+    /// Append every query, insert, update and delete with its timing to this
+    /// workload file, for `postgres-mcp replay`. `None` disables recording.
+    pub record_workload: Option<Arc<WorkloadRecorder>>,
+    /// Schema that qualifies unqualified table and index names in the
+    /// identifier-based tools (describe, drop_table, drop_index, ...).
+    /// `None` uses `public`.
+    pub default_schema: Option<String>,
+    /// Directory copy_from_file may read CSV files from. `None` disables the
+    /// tool.
+    pub import_dir: Option<PathBuf>,
+    /// Include the SQL sent to Postgres, after rewrites such as IN-list
+    /// expansion, as `effective_sql` in the envelope and typed query formats
+    /// and in paginate_query pages.
+    pub echo_effective_sql: bool,
+    /// How the query tool turns result rows into JSON.
+    pub serialization_mode: SerializationMode,
+    /// Most rows a query call returns when it sets no limit. `None` uses
+    /// 1000, and 0 returns every row.
+    pub default_row_limit: Option<u32>,
+    /// How long a transaction opened with begin_transaction may stay idle
+    /// before it is rolled back. `None` uses 5 minutes.
+    pub tx_idle_timeout: Option<Duration>,
+    /// Most connections a single session may have registered at a time.
+    /// `None` doesn't limit them.
+    pub max_conns_per_session: Option<usize>,
+    /// File the registered connections, passwords included, are saved to on
+    /// every register and unregister, and restored from on startup. `None`
+    /// keeps them in memory only.
+    pub registry_file: Option<PathBuf>,
+    /// Statement kinds the execute_ddl tool runs, named after sqlparser's
+    /// `Statement` variants (`AlterTable`, `Truncate`, ...), where a trailing
+    /// `*` matches a prefix. `None` allows `Alter*`, `Truncate`, `Grant` and
+    /// `CreateExtension`.
+    pub ddl_allowlist: Option<Vec<String>>,
+    /// Longest statement_timeout of a query, insert, update or delete call,
+    /// also applied to calls that set no timeout_secs. `None` doesn't limit
+    /// them.
+    pub max_statement_timeout: Option<Duration>,
+    /// How long a registered connection may go unused before it is
+    /// unregistered and its pool closed. `None` uses 30 minutes, and zero
+    /// keeps connections until they are unregistered.
+    pub conn_idle_ttl: Option<Duration>,
+}
+
+/// How the query tool serializes a result set.
+///
+/// Aggregating builds the whole JSON array in Postgres and sends it as one
+/// value, which is fine for small results. Streaming renders each row
+/// to JSON in Postgres but sends the rows one by one, so neither side has to
+/// hold a single huge value. See `benchmark_serialization_modes` in
+/// `src/pg.rs` for the benchmark behind the `auto` threshold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SerializationMode {
+    /// Aggregate queries with a small LIMIT and stream everything else
+    #[default]
+    Auto,
+    /// Always aggregate the result into a single JSON array
+    Aggregate,
+    /// Always stream rows one at a time
+    Stream,
+}
+
+/// Deadlines for writes wrapped in a transaction, set with `SET LOCAL`.
+/// A value of 0 disables the corresponding timeout, as in Postgres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteTxConfig {
+    pub statement_timeout_ms: u64,
+    pub lock_timeout_ms: u64,
+}
+
+/// Bearer tokens accepted by the SSE server and the tools each may call,
+/// loaded from the JSON file passed to `--auth-config`:
+///
+/// ```json
+/// {
+///   "tokens": { "s3cret-reader": "reader", "s3cret-admin": "admin" },
+///   "roles": { "reader": ["register", "query", "list_tables"], "admin": ["*"] }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// The role of each bearer token.
+    pub tokens: HashMap<String, String>,
+    /// The tools each role may call; `*` allows every tool.
+    pub roles: HashMap<String, HashSet<String>>,
+}
+
+/// The role a session authenticated as, with the tools it may call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRole {
+    pub name: String,
+    pub tools: HashSet<String>,
+}
+
+impl AuthConfig {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let config: Self = serde_json::from_str(json)?;
+        if let Some((_, role)) = config
+            .tokens
+            .iter()
+            .find(|(_, role)| !config.roles.contains_key(*role))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("A token has role '{}', which is not in roles", role),
+            ));
+        }
+        Ok(config)
+    }
+
+    /// The role of a bearer token, or `None` if the token is unknown.
+    pub fn role(&self, token: &str) -> Option<ToolRole> {
+        let name = self.tokens.get(token)?;
+        Some(ToolRole {
+            name: name.clone(),
+            tools: self.roles[name].clone(),
+        })
+    }
+}
+
+impl ToolRole {
+    pub fn allows(&self, tool: &str) -> bool {
+        self.tools.contains("*") || self.tools.contains(tool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_config_should_map_tokens_to_tools() {
+        let config = AuthConfig::from_json(
+            r#"{
+              "tokens": { "t1": "reader", "t2": "admin" },
+              "roles": { "reader": ["query", "list_tables"], "admin": ["*"] }
+            }"#,
+        )
+        .unwrap();
+
+        let reader = config.role("t1").unwrap();
+        assert_eq!(reader.name, "reader");
+        assert!(reader.allows("query"));
+        assert!(!reader.allows("drop_table"));
+        assert!(config.role("t2").unwrap().allows("drop_table"));
+        assert!(config.role("unknown").is_none());
+
+        let missing_role = r#"{ "tokens": { "t1": "writer" }, "roles": {} }"#;
+        assert!(AuthConfig::from_json(missing_role).is_err());
+    }
+}
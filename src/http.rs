@@ -0,0 +1,290 @@
+//! Streamable HTTP transport for the MCP server.
+//!
+//! rmcp 0.1 only ships the SSE transport, so this implements the Streamable
+//! HTTP transport of the 2025-03-26 MCP spec on the same lines as `sse.rs`.
+//! Clients POST every JSON-RPC message to one endpoint and get the response
+//! to a request in the body. Sessions start with `initialize`, which returns
+//! their ID in the `Mcp-Session-Id` header, and end with a DELETE. The
+//! server never sends requests of its own, so it doesn't offer the optional
+//! GET stream for them.
+
+use crate::{AuthConfig, ToolRole, sse::bearer_role};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use futures::SinkExt;
+use rmcp::{
+    RoleServer, Service, ServiceExt,
+    model::{
+        ClientJsonRpcMessage, ClientRequest, JsonRpcMessage, JsonRpcRequest, RequestId,
+        ServerJsonRpcMessage,
+    },
+    service::{RxJsonRpcMessage, TxJsonRpcMessage},
+};
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+use tokio::sync::{Mutex, RwLock, mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::{CancellationToken, PollSender};
+use tracing::Instrument;
+
+const SESSION_ID_HEADER: HeaderName = HeaderName::from_static("mcp-session-id");
+
+type SessionId = Arc<str>;
+type SessionStore = Arc<RwLock<HashMap<SessionId, Session>>>;
+/// Requests of a session waiting for their response, by request ID.
+type PendingRequests = Arc<Mutex<HashMap<RequestId, oneshot::Sender<ServerJsonRpcMessage>>>>;
+
+#[derive(Debug, Clone)]
+struct Session {
+    tx: mpsc::Sender<ClientJsonRpcMessage>,
+    pending: PendingRequests,
+    /// Name of the role the session authenticated as.
+    role: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamableHttpServerConfig {
+    pub bind: SocketAddr,
+    pub path: String,
+    /// Bearer tokens accepted on the endpoint and the tools each may call.
+    /// `None` accepts every client.
+    pub auth: Option<Arc<AuthConfig>>,
+    pub ct: CancellationToken,
+}
+
+/// A transport for one session: messages POSTed by the client come in on
+/// `stream`, responses sent to `sink` go back in the body of their POST.
+pub struct StreamableHttpServerTransport {
+    session_id: SessionId,
+    role: Option<ToolRole>,
+    stream: ReceiverStream<RxJsonRpcMessage<RoleServer>>,
+    sink: PollSender<TxJsonRpcMessage<RoleServer>>,
+}
+
+#[derive(Clone)]
+struct App {
+    sessions: SessionStore,
+    transport_tx: mpsc::UnboundedSender<StreamableHttpServerTransport>,
+    auth: Option<Arc<AuthConfig>>,
+}
+
+impl App {
+    async fn session(&self, headers: &HeaderMap) -> Result<(SessionId, Session), StatusCode> {
+        let id = headers
+            .get(&SESSION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        self.sessions
+            .read()
+            .await
+            .get_key_value(id)
+            .map(|(id, session)| (id.clone(), session.clone()))
+            .ok_or(StatusCode::NOT_FOUND)
+    }
+
+    async fn start_session(
+        &self,
+        role: Option<ToolRole>,
+    ) -> Result<(SessionId, Session), StatusCode> {
+        let session_id: SessionId = uuid::Uuid::new_v4().simple().to_string().into();
+        tracing::info!(%session_id, role = role.as_ref().map(|role| role.name.as_str()), "streamable http session");
+
+        let (from_client_tx, from_client_rx) = mpsc::channel(64);
+        let (to_client_tx, to_client_rx) = mpsc::channel(64);
+        let session = Session {
+            tx: from_client_tx,
+            pending: PendingRequests::default(),
+            role: role.as_ref().map(|role| role.name.clone()),
+        };
+        self.sessions
+            .write()
+            .await
+            .insert(session_id.clone(), session.clone());
+        tokio::spawn(route_responses(to_client_rx, session.pending.clone()));
+
+        let transport = StreamableHttpServerTransport {
+            session_id: session_id.clone(),
+            role,
+            stream: ReceiverStream::new(from_client_rx),
+            sink: PollSender::new(to_client_tx),
+        };
+        if self.transport_tx.send(transport).is_err() {
+            tracing::warn!("server is closed, dropping streamable http session");
+            self.sessions.write().await.remove(&session_id);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Ok((session_id, session))
+    }
+}
+
+// Hand each response the service sends to the POST waiting for it. Pending
+// POSTs fail once the service is gone and this drops their senders.
+async fn route_responses(mut rx: mpsc::Receiver<ServerJsonRpcMessage>, pending: PendingRequests) {
+    while let Some(message) = rx.recv().await {
+        let id = match &message {
+            JsonRpcMessage::Response(response) => &response.id,
+            JsonRpcMessage::Error(error) => &error.id,
+            _ => {
+                tracing::debug!(
+                    ?message,
+                    "dropping server message with no request to answer"
+                );
+                continue;
+            }
+        };
+        if let Some(tx) = pending.lock().await.remove(id) {
+            let _ = tx.send(message);
+        }
+    }
+    pending.lock().await.clear();
+}
+
+async fn post_handler(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Json(message): Json<ClientJsonRpcMessage>,
+) -> Result<Response, StatusCode> {
+    tracing::debug!(?message, "new client message");
+    let role = bearer_role(app.auth.as_deref(), &headers)?;
+    let initialize = matches!(
+        &message,
+        JsonRpcMessage::Request(JsonRpcRequest {
+            request: ClientRequest::InitializeRequest(_),
+            ..
+        })
+    );
+    let (session_id, session) = if initialize && !headers.contains_key(&SESSION_ID_HEADER) {
+        app.start_session(role).await?
+    } else {
+        let (session_id, session) = app.session(&headers).await?;
+        // A token of another role can't post into the session.
+        if role.map(|role| role.name) != session.role {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        (session_id, session)
+    };
+
+    let response = match &message {
+        JsonRpcMessage::Request(request) => {
+            let (tx, rx) = oneshot::channel();
+            session.pending.lock().await.insert(request.id.clone(), tx);
+            Some(rx)
+        }
+        _ => None,
+    };
+    if session.tx.send(message).await.is_err() {
+        tracing::error!("send message error");
+        return Err(StatusCode::GONE);
+    }
+    let Some(response) = response else {
+        return Ok(StatusCode::ACCEPTED.into_response());
+    };
+    let response = response.await.map_err(|_| StatusCode::GONE)?;
+    Ok((
+        [(SESSION_ID_HEADER, session_id.to_string())],
+        Json(response),
+    )
+        .into_response())
+}
+
+async fn delete_handler(
+    State(app): State<App>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    let role = bearer_role(app.auth.as_deref(), &headers)?;
+    let (session_id, session) = app.session(&headers).await?;
+    if role.map(|role| role.name) != session.role {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // Dropping the last sender ends the session's stream, which stops its
+    // service.
+    app.sessions.write().await.remove(&session_id);
+    tracing::info!(%session_id, "streamable http session closed by client");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug)]
+pub struct StreamableHttpServer {
+    transport_rx: mpsc::UnboundedReceiver<StreamableHttpServerTransport>,
+    sessions: SessionStore,
+    pub config: StreamableHttpServerConfig,
+}
+
+impl StreamableHttpServer {
+    pub async fn serve_with_config(config: StreamableHttpServerConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = mpsc::unbounded_channel();
+        let sessions = SessionStore::default();
+        let app = App {
+            sessions: sessions.clone(),
+            transport_tx,
+            auth: config.auth.clone(),
+        };
+
+        let listener = tokio::net::TcpListener::bind(config.bind).await?;
+        let router = Router::new()
+            .route(&config.path, post(post_handler).delete(delete_handler))
+            .with_state(app);
+        let ct = config.ct.child_token();
+        let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+            ct.cancelled().await;
+            tracing::info!("streamable http server cancelled");
+        });
+        tokio::spawn(
+            async move {
+                if let Err(e) = server.await {
+                    tracing::error!(error = %e, "streamable http server shutdown with error");
+                }
+            }
+            .instrument(tracing::info_span!("streamable-http-server", bind_address = %config.bind)),
+        );
+
+        Ok(Self {
+            transport_rx,
+            sessions,
+            config,
+        })
+    }
+
+    /// Serve every new session with a service from `service_provider`, given
+    /// the role the session authenticated as. Returns the token that shuts
+    /// the sessions down.
+    pub fn with_service<S, F>(mut self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn(Option<ToolRole>) -> S + Send + 'static,
+    {
+        let ct = self.config.ct.clone();
+        tokio::spawn(async move {
+            while let Some(mut transport) = self.transport_rx.recv().await {
+                let service = service_provider(transport.role.take());
+                let ct = self.config.ct.child_token();
+                let sessions = self.sessions.clone();
+                tokio::spawn(async move {
+                    let StreamableHttpServerTransport {
+                        session_id,
+                        stream,
+                        sink,
+                        ..
+                    } = transport;
+                    let sink = sink.sink_map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e));
+                    match service.serve_with_ct((sink, stream), ct).await {
+                        Ok(server) => {
+                            if let Err(e) = server.waiting().await {
+                                tracing::error!(%session_id, error = %e, "streamable http session failed");
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!(%session_id, error = %e, "streamable http session failed")
+                        }
+                    }
+                    sessions.write().await.remove(&session_id);
+                });
+            }
+        });
+        ct
+    }
+}
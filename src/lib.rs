@@ -1,4 +1,17 @@
+mod config;
+mod http;
 mod mcp;
 mod pg;
+mod prompts;
+mod rate_limit;
+mod registry;
+mod sse;
+mod testdata;
+mod transactions;
+mod workload;
 
+pub use config::{AuthConfig, PgMcpConfig, SerializationMode, ToolRole, WriteTxConfig};
+pub use http::{StreamableHttpServer, StreamableHttpServerConfig};
 pub use pg::{Conns, PgMcp};
+pub use sse::{SseServer, SseServerConfig};
+pub use workload::{LatencyStats, ReplayOptions, ReplayReport, WorkloadRecorder, replay_workload};
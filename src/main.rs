@@ -1,27 +1,173 @@
-use clap::{Parser, Subcommand};
-use postgres_mcp::PgMcp;
+use clap::{Args, Parser, Subcommand};
+use postgres_mcp::{
+    AuthConfig, PgMcp, PgMcpConfig, ReplayOptions, SerializationMode, SseServer, SseServerConfig,
+    StreamableHttpServer, StreamableHttpServerConfig, WorkloadRecorder, WriteTxConfig,
+    replay_workload,
+};
 use rmcp::ServiceExt;
-use rmcp::transport::sse_server::{SseServer, SseServerConfig};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Maximum tool calls per second per connection (disabled by default)
+    #[arg(long, global = true)]
+    rate_limit: Option<u32>,
+    /// Block all schema-modifying tools (create/drop table, index, schema, type)
+    #[arg(long, global = true)]
+    disable_ddl: bool,
+    /// Run each insert/update/delete in its own transaction with a statement and lock timeout
+    #[arg(long, global = true)]
+    wrap_writes_in_tx: bool,
+    /// statement_timeout for wrapped writes, in milliseconds (0 disables it)
+    #[arg(long, global = true, default_value_t = 30_000)]
+    write_statement_timeout_ms: u64,
+    /// lock_timeout for wrapped writes, in milliseconds (0 disables it)
+    #[arg(long, global = true, default_value_t = 5_000)]
+    write_lock_timeout_ms: u64,
+    /// Reject UPDATE/DELETE statements that join other tables via FROM or USING
+    #[arg(long, global = true)]
+    single_table_writes: bool,
+    /// Append every query/insert/update/delete with its timing to this file, for `replay`
+    #[arg(long, global = true)]
+    record_workload: Option<PathBuf>,
+    /// Schema for unqualified table/index names in describe, drop_table, drop_index, etc.
+    #[arg(long, global = true, default_value = "public")]
+    default_schema: String,
+    /// Directory copy_from_file may read CSV files from (the tool is disabled without it)
+    #[arg(long, global = true)]
+    import_dir: Option<PathBuf>,
+    /// Return the rewritten SQL sent to Postgres as effective_sql in enveloped query results
+    #[arg(long, global = true)]
+    echo_effective_sql: bool,
+    /// How query results are serialized: aggregated in Postgres, streamed row by row, or chosen by LIMIT
+    #[arg(long, global = true, value_enum, default_value_t = SerializationMode::Auto)]
+    serialization_mode: SerializationMode,
+    /// Most rows a query returns when the call sets no limit (0 disables the cap)
+    #[arg(long, global = true, default_value_t = 1000)]
+    default_row_limit: u32,
+    /// Roll back transactions from begin_transaction left idle for this many seconds
+    #[arg(long, global = true, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    tx_idle_timeout_secs: u64,
+    /// Most connections one client session may have registered at a time (unlimited by default)
+    #[arg(long, global = true)]
+    max_conns_per_session: Option<usize>,
+    /// Save registered connections, passwords included, to this file and restore them on startup
+    #[arg(long, global = true)]
+    registry_file: Option<PathBuf>,
+    /// Comma-separated statement kinds execute_ddl may run, e.g. AlterTable,Truncate (Alter*,Truncate,Grant,CreateExtension by default)
+    #[arg(long, global = true, value_delimiter = ',')]
+    ddl_allowlist: Option<Vec<String>>,
+    /// Cap in seconds on the timeout_secs of query/insert/update/delete calls, also applied to calls without one (unlimited by default)
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    max_statement_timeout_secs: Option<u64>,
+    /// Unregister connections left unused for this many seconds (0 keeps them until unregistered)
+    #[arg(long, global = true, default_value_t = 1800)]
+    conn_idle_ttl_secs: u64,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Run in stdio mode
     Stdio,
-    /// Run in SSE mode
+    /// Run a Streamable HTTP server, serving MCP at /mcp
+    Http {
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Run in SSE mode (deprecated, use `http`)
     Sse {
-        /// Port for the SSE server to bind to
-        #[arg(short, long, default_value_t = 3000)]
-        port: u16,
+        #[command(flatten)]
+        sse: SseArgs,
+    },
+    /// Run stdio and SSE at the same time, sharing registered connections
+    Both {
+        #[command(flatten)]
+        sse: SseArgs,
+    },
+    /// Re-run a workload recorded with --record-workload and report its latency
+    Replay {
+        /// Workload file written by --record-workload
+        file: PathBuf,
+        /// Postgres connection string of the database to replay against
+        #[arg(long)]
+        conn_str: String,
+        /// Number of statements to run at the same time
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        /// Only replay queries, skipping inserts, updates and deletes
+        #[arg(long)]
+        skip_writes: bool,
     },
 }
 
+#[derive(Args)]
+struct HttpArgs {
+    /// Port for the HTTP server to bind to
+    #[arg(short, long, default_value_t = 3000)]
+    port: u16,
+    /// JSON file mapping bearer tokens to roles and roles to the tools they may call
+    #[arg(long)]
+    auth_config: Option<PathBuf>,
+}
+
+impl HttpArgs {
+    fn into_server_config(
+        self,
+        ct: CancellationToken,
+    ) -> anyhow::Result<StreamableHttpServerConfig> {
+        Ok(StreamableHttpServerConfig {
+            bind: ([0, 0, 0, 0], self.port).into(),
+            path: "/mcp".to_string(),
+            auth: load_auth(self.auth_config)?,
+            ct,
+        })
+    }
+}
+
+#[derive(Args)]
+struct SseArgs {
+    /// Port for the SSE server to bind to
+    #[arg(short, long, default_value_t = 3000)]
+    port: u16,
+    /// Seconds between keep-alive comments on idle SSE streams (0 disables them)
+    #[arg(long, default_value_t = 15)]
+    sse_keepalive_secs: u64,
+    /// JSON file mapping bearer tokens to roles and roles to the tools they may call
+    #[arg(long)]
+    auth_config: Option<PathBuf>,
+}
+
+impl SseArgs {
+    fn into_server_config(self, ct: CancellationToken) -> anyhow::Result<SseServerConfig> {
+        tracing::warn!(
+            "The SSE transport is deprecated and will be removed; use `postgres-mcp http` (Streamable HTTP) instead"
+        );
+        let keep_alive =
+            (self.sse_keepalive_secs > 0).then(|| Duration::from_secs(self.sse_keepalive_secs));
+        Ok(SseServerConfig {
+            bind: ([0, 0, 0, 0], self.port).into(),
+            sse_path: "/sse".to_string(),
+            post_path: "/message".to_string(),
+            keep_alive,
+            auth: load_auth(self.auth_config)?,
+            ct,
+        })
+    }
+}
+
+fn load_auth(path: Option<PathBuf>) -> anyhow::Result<Option<Arc<AuthConfig>>> {
+    Ok(path
+        .map(|path| AuthConfig::load(path).map(Arc::new))
+        .transpose()?)
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize the tracing subscriber with file and stdout logging
@@ -32,50 +178,126 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let cli = Cli::parse();
+    let config = PgMcpConfig {
+        rate_limit: cli.rate_limit,
+        disable_ddl: cli.disable_ddl,
+        wrap_writes_in_tx: cli.wrap_writes_in_tx.then_some(WriteTxConfig {
+            statement_timeout_ms: cli.write_statement_timeout_ms,
+            lock_timeout_ms: cli.write_lock_timeout_ms,
+        }),
+        single_table_writes: cli.single_table_writes,
+        record_workload: cli
+            .record_workload
+            .map(|path| WorkloadRecorder::open(path).map(Arc::new))
+            .transpose()?,
+        default_schema: Some(cli.default_schema),
+        import_dir: cli.import_dir.map(std::fs::canonicalize).transpose()?,
+        echo_effective_sql: cli.echo_effective_sql,
+        serialization_mode: cli.serialization_mode,
+        default_row_limit: Some(cli.default_row_limit),
+        tx_idle_timeout: Some(Duration::from_secs(cli.tx_idle_timeout_secs)),
+        max_conns_per_session: cli.max_conns_per_session,
+        registry_file: cli.registry_file,
+        ddl_allowlist: cli.ddl_allowlist,
+        max_statement_timeout: cli.max_statement_timeout_secs.map(Duration::from_secs),
+        conn_idle_ttl: Some(Duration::from_secs(cli.conn_idle_ttl_secs)),
+    };
 
     match cli.command {
-        Commands::Stdio => run_stdio_mode().await?,
-        Commands::Sse { port } => run_sse_mode(port).await?,
+        Commands::Stdio => run_stdio_mode(config).await?,
+        Commands::Http { http } => run_http_mode(http, config).await?,
+        Commands::Sse { sse } => run_sse_mode(sse, config).await?,
+        Commands::Both { sse } => run_both_mode(sse, config).await?,
+        Commands::Replay {
+            file,
+            conn_str,
+            concurrency,
+            skip_writes,
+        } => {
+            let options = ReplayOptions {
+                concurrency,
+                skip_writes,
+            };
+            let report = replay_workload(file, &conn_str, options).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
     }
 
     Ok(())
 }
 
-async fn run_stdio_mode() -> anyhow::Result<()> {
+async fn run_stdio_mode(config: PgMcpConfig) -> anyhow::Result<()> {
     tracing::info!("Starting Postgres MCP server in stdio mode");
 
+    // Started by hand in a terminal, the server would sit waiting for input
+    // with no sign of life, so explain what it expects.
+    if std::io::stdin().is_terminal() {
+        eprintln!(
+            "postgres-mcp is waiting for MCP JSON-RPC messages on stdin.\n\
+             It is meant to be launched by an MCP client (e.g. Cursor), not run interactively.\n\
+             To run a standalone server, use `postgres-mcp http --port <port>` instead. Press Ctrl-C to exit."
+        );
+    }
+
     // Create an instance of our PostgresMcp router
-    let service = PgMcp::new()
-        .serve(rmcp::transport::stdio())
-        .await
-        .inspect_err(|e| {
-            tracing::error!("serving error: {:?}", e);
-        })?;
+    let mcp = PgMcp::with_config(config);
+    mcp.restore_connections().await;
+    let service = mcp.serve(rmcp::transport::stdio()).await.inspect_err(|e| {
+        tracing::error!("serving error: {:?}", e);
+    })?;
 
     service.waiting().await?;
 
     Ok(())
 }
 
-async fn run_sse_mode(port: u16) -> anyhow::Result<()> {
-    tracing::info!("Starting Postgres MCP server in SSE mode on port {}", port);
+async fn run_http_mode(http: HttpArgs, mcp_config: PgMcpConfig) -> anyhow::Result<()> {
+    // Every session has its own connections, so there is no one set to save.
+    if mcp_config.registry_file.is_some() {
+        anyhow::bail!(
+            "--registry-file needs connections shared by all sessions; use the `both` or `stdio` mode"
+        );
+    }
+    tracing::info!(
+        "Starting Postgres MCP server in Streamable HTTP mode on port {}",
+        http.port
+    );
 
-    let addr = format!("0.0.0.0:{}", port);
-    // Store bind address and cancellation token separately
-    let bind_addr: std::net::SocketAddr = addr.parse()?;
-    let ct_main = tokio_util::sync::CancellationToken::new();
+    let ct_main = CancellationToken::new();
+    let config = http.into_server_config(ct_main.clone())?;
+    let http_server = StreamableHttpServer::serve_with_config(config).await?;
 
-    let config = SseServerConfig {
-        bind: bind_addr, // Use stored address
-        sse_path: "/sse".to_string(),
-        post_path: "/message".to_string(),
-        // Clone the token for the config
-        ct: ct_main.clone(),
-    };
+    // Each session gets its own PgMcp, as in SSE mode
+    let service_ct = http_server
+        .with_service(move |role| PgMcp::with_config(mcp_config.clone()).with_role(role));
 
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Ctrl-C received, shutting down...");
+    service_ct.cancel();
+    ct_main.cancel();
+
+    Ok(())
+}
+
+async fn run_sse_mode(sse: SseArgs, mcp_config: PgMcpConfig) -> anyhow::Result<()> {
+    // Every session has its own connections, so there is no one set to save.
+    if mcp_config.registry_file.is_some() {
+        anyhow::bail!(
+            "--registry-file needs connections shared by all sessions; use the `both` or `stdio` mode"
+        );
+    }
+    tracing::info!(
+        "Starting Postgres MCP server in SSE mode on port {}",
+        sse.port
+    );
+
+    let ct_main = CancellationToken::new();
+    let config = sse.into_server_config(ct_main.clone())?;
     let sse_server = SseServer::serve_with_config(config).await?;
 
-    let service_ct = sse_server.with_service(PgMcp::new);
+    // Each SSE session gets its own PgMcp, so rate limits are per session
+    let service_ct =
+        sse_server.with_service(move |role| PgMcp::with_config(mcp_config.clone()).with_role(role));
 
     tokio::signal::ctrl_c().await?;
     tracing::info!("Ctrl-C received, shutting down...");
@@ -85,3 +307,43 @@ async fn run_sse_mode(port: u16) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn run_both_mode(sse: SseArgs, mcp_config: PgMcpConfig) -> anyhow::Result<()> {
+    tracing::info!(
+        "Starting Postgres MCP server in stdio and SSE mode on port {}",
+        sse.port
+    );
+
+    let ct_main = CancellationToken::new();
+    let config = sse.into_server_config(ct_main.clone())?;
+    let sse_server = SseServer::serve_with_config(config).await?;
+
+    // Clones share the registered connections (and their rate limits), so a
+    // connection registered over one transport can be used from the other.
+    // Each session still has its own --max-conns-per-session quota.
+    let mcp = PgMcp::with_config(mcp_config);
+    mcp.restore_connections().await;
+    let sse_mcp = mcp.clone();
+    let service_ct = sse_server.with_service(move |role| sse_mcp.for_new_session().with_role(role));
+
+    let service = mcp.serve(rmcp::transport::stdio()).await.inspect_err(|e| {
+        tracing::error!("serving error: {:?}", e);
+    })?;
+
+    // Closing stdin stops the SSE server too, as the process would exit if
+    // it ran stdio alone.
+    tokio::select! {
+        quit = service.waiting() => {
+            quit?;
+            tracing::info!("stdin closed, shutting down...");
+        }
+        ctrl_c = tokio::signal::ctrl_c() => {
+            ctrl_c?;
+            tracing::info!("Ctrl-C received, shutting down...");
+        }
+    }
+    service_ct.cancel();
+    ct_main.cancel();
+
+    Ok(())
+}
@@ -1,16 +1,160 @@
-use crate::pg::PgMcpError;
-use crate::{Conns, PgMcp};
+use crate::pg::{
+    self, Compression, ConnOptions, CopyOptions, CountComparison, DEFAULT_CONN_IDLE_TTL,
+    DEFAULT_ROW_LIMIT, DropOptions, PgMcpError, QueryFormat, QueryOptions, Registration,
+    ValidationErrorKind, WriteOptions,
+};
+use crate::prompts;
+use crate::rate_limit::RateLimiter;
+use crate::registry::Registry;
+use crate::transactions::{DEFAULT_TX_IDLE_TIMEOUT, Transactions};
+use crate::workload::WorkloadTool;
+use crate::{Conns, PgMcp, PgMcpConfig, ToolRole};
 use anyhow::Result;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
 use rmcp::{
-    Error as McpError, ServerHandler,
-    model::{CallToolResult, Content, ServerCapabilities, ServerInfo},
-    schemars, tool,
+    Error as McpError, RoleServer, ServerHandler,
+    handler::server::tool::ToolCallContext,
+    model::{
+        AnnotateAble, CallToolRequestParam, CallToolResult, Content, GetPromptRequestParam,
+        GetPromptResult, ListPromptsResult, ListResourcesResult, ListToolsResult,
+        PaginatedRequestParam, RawResource, ReadResourceRequestParam, ReadResourceResult,
+        ResourceContents, ServerCapabilities, ServerInfo,
+    },
+    schemars,
+    service::RequestContext,
+    tool,
 };
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::Instrument;
+
+/// Longest `intent` kept in logs, in characters.
+const MAX_INTENT_CHARS: usize = 1000;
+
+tokio::task_local! {
+    // Intent of the tool call being served, for the workload file.
+    static CALL_INTENT: Option<String>;
+}
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct RegisterRequest {
     #[schemars(description = "Postgres connection string")]
     pub conn_str: String,
+    #[serde(flatten)]
+    pub options: RegisterOptions,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RegisterEnvRequest {
+    #[schemars(
+        description = "Name of the server environment variable holding the Postgres connection string, e.g. DATABASE_URL"
+    )]
+    pub env_var: String,
+    #[serde(flatten)]
+    pub options: RegisterOptions,
+}
+
+/// Options of register and register_env.
+#[derive(Debug, Clone, serde::Deserialize, schemars::JsonSchema)]
+pub struct RegisterOptions {
+    #[schemars(
+        description = "Set to true when connecting through PgBouncer in transaction-pooling mode. Disables prepared statement caching; describe_query is unavailable on such connections."
+    )]
+    #[serde(default)]
+    pub pgbouncer: bool,
+    #[schemars(
+        description = "If the database is a standby in recovery, only allow read tools on this connection"
+    )]
+    #[serde(default)]
+    pub reads_only_if_standby: bool,
+    #[schemars(
+        description = "Only allow read tools on this connection. Write and DDL tools are rejected before reaching the database, and its sessions default to read-only transactions (except through PgBouncer)"
+    )]
+    #[serde(default)]
+    pub read_only: bool,
+    #[schemars(
+        description = "How long a tool call waits for a free pooled connection before failing with a pool exhausted error, in seconds. Defaults to 30"
+    )]
+    #[serde(default)]
+    pub acquire_timeout_secs: Option<u64>,
+    #[schemars(
+        description = "application_name reported by the connection's backends in pg_stat_activity"
+    )]
+    #[serde(default)]
+    pub application_name: Option<String>,
+    #[schemars(
+        description = "Most database connections the connection's pool opens. Defaults to 10; lower it for small databases shared by many connections"
+    )]
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    #[schemars(
+        description = "Database connections the pool keeps open even when idle. Defaults to 0"
+    )]
+    #[serde(default)]
+    pub min_connections: Option<u32>,
+    #[schemars(
+        description = "Seconds a pooled connection may sit idle before it is closed. Defaults to 600"
+    )]
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
+    #[schemars(
+        description = "Schemas whose tables the connection may reference. Together with allowed_tables, restricts the connection to the listed tables; statements referencing others fail validation. Unrestricted if both are empty"
+    )]
+    #[serde(default)]
+    pub allowed_schemas: Vec<String>,
+    #[schemars(
+        description = "Tables, as name or schema.name, the connection may reference besides those of allowed_schemas. Unqualified names are in the default schema"
+    )]
+    #[serde(default)]
+    pub allowed_tables: Vec<String>,
+}
+
+impl RegisterOptions {
+    fn conn_options(&self) -> ConnOptions {
+        ConnOptions {
+            pgbouncer: self.pgbouncer,
+            reads_only_if_standby: self.reads_only_if_standby,
+            read_only: self.read_only,
+            acquire_timeout: self.acquire_timeout_secs.map(Duration::from_secs),
+            application_name: self.application_name.clone(),
+            max_connections: self.max_connections,
+            min_connections: self.min_connections,
+            idle_timeout: self.idle_timeout_secs.map(Duration::from_secs),
+            allowed_schemas: self.allowed_schemas.clone(),
+            allowed_tables: self.allowed_tables.clone(),
+        }
+    }
+
+    // The result of a registration: the connection ID, then its connect
+    // timings and a warning if the database is a standby.
+    fn registered(&self, registration: Registration) -> CallToolResult {
+        let mut notice = serde_json::json!({ "connect_timings": registration.timings });
+        if registration.in_recovery {
+            let warning = if self.reads_only_if_standby || self.read_only {
+                "The database is a standby in recovery; this connection is restricted to read tools."
+            } else {
+                "The database is a standby in recovery; write tools will be rejected by the server."
+            };
+            notice["in_recovery"] = true.into();
+            notice["warning"] = warning.into();
+        }
+        CallToolResult::success(vec![
+            Content::text(registration.id),
+            Content::text(notice.to_string()),
+        ])
+    }
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ImportConnectionsRequest {
+    #[schemars(description = "Connections as returned by export_connections")]
+    pub connections: Vec<pg::ExportedConnection>,
+    #[schemars(
+        description = "Passwords by connection ID. Connections without one use PGPASSWORD or ~/.pgpass of the server"
+    )]
+    #[serde(default)]
+    pub passwords: HashMap<String, String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -24,11 +168,152 @@ pub struct QueryRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
     #[schemars(
-        description = "Single SQL query, could return multiple rows. Caller should properly limit the number of rows returned."
+        description = "Single SQL query, could return multiple rows. Results are capped by limit, or by the server's default row limit"
+    )]
+    pub query: String,
+    #[schemars(
+        description = "Values for the $1, $2, ... placeholders of the query. A JSON array bound to `IN ($n)` is expanded to `= ANY($n)`, so it matches any of its elements"
+    )]
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+    #[schemars(
+        description = "Postgres type of each param by position, e.g. [\"int4\", null]. Used for null params, which otherwise bind as text and can fail with a type mismatch. Alternatively cast the placeholder in the SQL, e.g. $1::int4"
+    )]
+    #[serde(default)]
+    pub param_types: Vec<Option<String>>,
+    #[schemars(
+        description = "Result format: 'rows' (default) returns a JSON array of rows, 'envelope' returns { row_count, rows, truncated }, 'typed' returns { columns: [{ name, pg_type }], row_count, rows, truncated } with the Postgres type name of each column. A truncated result is always returned as an envelope"
+    )]
+    #[serde(default)]
+    pub format: QueryFormat,
+    #[schemars(
+        description = "Return the column names and Postgres types with the rows, as { columns: [{ name, pg_type }], row_count, rows, truncated }. Same as format 'typed', and takes precedence over format"
+    )]
+    #[serde(default)]
+    pub include_metadata: bool,
+    #[schemars(
+        description = "Tag the backend with this application_name (SET LOCAL) while it runs this call, e.g. a request ID to find the call in pg_stat_activity"
+    )]
+    #[serde(default)]
+    pub application_name: Option<String>,
+    #[schemars(
+        description = "Run this call as another role (SET LOCAL ROLE), with that role's privileges and row-level security. The connecting user must be a member of it; the connection reverts to its own role when the call ends"
+    )]
+    #[serde(default)]
+    pub run_as_role: Option<String>,
+    #[schemars(
+        description = "Add a __row field with the 1-based position of each row, to refer to rows by number. Fails if the query returns a column named __row"
+    )]
+    #[serde(default)]
+    pub with_row_numbers: bool,
+    #[schemars(
+        description = "Return a single-row result as [{ field, value }] pairs in column order instead of one object, e.g. for a wide status row. Fails if the query returns more than one row; no rows give []"
+    )]
+    #[serde(default)]
+    pub transpose: bool,
+    #[schemars(
+        description = "Cancel the query after this many seconds (statement_timeout). Rows are streamed, so the ones received before the timeout are returned as { row_count, rows, timed_out: true, message } instead of an error"
+    )]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[schemars(
+        description = "Run this call in the transaction with this ID from begin_transaction instead of on its own. Can't be combined with timeout_secs, application_name or run_as_role"
+    )]
+    #[serde(default)]
+    pub tx_id: Option<String>,
+    #[schemars(
+        description = "Return at most this many rows (default: the server's --default-row-limit, 1000 unless configured). truncated is true when more rows exist"
+    )]
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[schemars(description = "Skip this many rows before returning any, to fetch the next slice")]
+    #[serde(default)]
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PaginateQueryRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Single SQL SELECT query to paginate, without ORDER BY or LIMIT")]
+    pub query: String,
+    #[schemars(
+        description = "Result column to order and paginate by. It must be unique and not null, e.g. the primary key"
+    )]
+    pub order_by: String,
+    #[schemars(description = "Number of rows per page (default 100, max 1000)")]
+    #[serde(default = "default_page_size")]
+    pub page_size: u32,
+    #[schemars(
+        description = "next_cursor returned with the previous page. Omit for the first page"
+    )]
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+fn default_page_size() -> u32 {
+    100
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeQueryRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Single SQL SELECT query to describe. It will not be executed.")]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AssertCountRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Single SQL SELECT query returning one row with one integer column, e.g. SELECT COUNT(*) FROM orders WHERE customer_id IS NULL"
+    )]
+    pub query: String,
+    #[schemars(description = "Value to compare the query's result to")]
+    pub expected: i64,
+    #[schemars(
+        description = "Comparison that must hold: 'eq' (actual = expected), 'gte' (actual >= expected) or 'lte' (actual <= expected)"
+    )]
+    pub comparison: CountComparison,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExplainRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Single SQL SELECT, INSERT, UPDATE or DELETE statement to explain")]
+    pub query: String,
+    #[schemars(
+        description = "Run the statement (EXPLAIN ANALYZE) to report actual row counts and timings. Writes are rolled back, but the statement still takes its locks and fires its triggers while it runs"
+    )]
+    #[serde(default)]
+    pub analyze: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EstimateQueryMemoryRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Single SQL SELECT query to estimate, without parameters. It will be planned but not executed."
     )]
     pub query: String,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ParseSqlRequest {
+    #[schemars(description = "SQL to parse. It is not executed and may hold several statements")]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ClassifyStatementRequest {
+    #[schemars(description = "A single SQL statement to classify. It is not executed")]
+    pub query: String,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct InsertRequest {
     #[schemars(description = "Connection ID")]
@@ -37,6 +322,41 @@ pub struct InsertRequest {
         description = "Single SQL insert statement, but multiple rows for the same table are allowed"
     )]
     pub query: String,
+    #[schemars(
+        description = "Insert each VALUES row in its own savepoint, committing the rows that succeed and returning the failed ones as errors: [{ row_index, message }]"
+    )]
+    #[serde(default)]
+    pub continue_on_error: bool,
+    #[schemars(
+        description = "Values for the $1, $2, ... placeholders of the statement. Not supported with continue_on_error"
+    )]
+    #[serde(default)]
+    pub params: Vec<serde_json::Value>,
+    #[schemars(
+        description = "Postgres type of each param by position, e.g. [\"int4\", null]. Used for null params, which otherwise bind as text and can fail with a type mismatch. Alternatively cast the placeholder in the SQL, e.g. $1::int4"
+    )]
+    #[serde(default)]
+    pub param_types: Vec<Option<String>>,
+    #[schemars(
+        description = "Tag the backend with this application_name (SET LOCAL) while it runs this call, e.g. a request ID to find the call in pg_stat_activity"
+    )]
+    #[serde(default)]
+    pub application_name: Option<String>,
+    #[schemars(
+        description = "Run this call as another role (SET LOCAL ROLE), with that role's privileges and row-level security. The connecting user must be a member of it; the connection reverts to its own role when the call ends"
+    )]
+    #[serde(default)]
+    pub run_as_role: Option<String>,
+    #[schemars(
+        description = "Run this call in the transaction with this ID from begin_transaction instead of on its own. Can't be combined with continue_on_error, application_name, run_as_role or timeout_secs"
+    )]
+    #[serde(default)]
+    pub tx_id: Option<String>,
+    #[schemars(
+        description = "Cancel the statement after this many seconds (statement_timeout). A cancelled write is rolled back and fails with a query timeout error"
+    )]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -47,6 +367,26 @@ pub struct UpdateRequest {
         description = "Single SQL update statement, could update multiple rows for the same table based on the WHERE clause"
     )]
     pub query: String,
+    #[schemars(
+        description = "Tag the backend with this application_name (SET LOCAL) while it runs this call, e.g. a request ID to find the call in pg_stat_activity"
+    )]
+    #[serde(default)]
+    pub application_name: Option<String>,
+    #[schemars(
+        description = "Run this call as another role (SET LOCAL ROLE), with that role's privileges and row-level security. The connecting user must be a member of it; the connection reverts to its own role when the call ends"
+    )]
+    #[serde(default)]
+    pub run_as_role: Option<String>,
+    #[schemars(
+        description = "Run this call in the transaction with this ID from begin_transaction instead of on its own. Can't be combined with application_name, run_as_role or timeout_secs"
+    )]
+    #[serde(default)]
+    pub tx_id: Option<String>,
+    #[schemars(
+        description = "Cancel the statement after this many seconds (statement_timeout). A cancelled write is rolled back and fails with a query timeout error"
+    )]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -57,236 +397,2035 @@ pub struct DeleteRequest {
         description = "Single SQL delete statement, could delete multiple rows for the same table based on the WHERE clause"
     )]
     pub query: String,
+    #[schemars(
+        description = "Tag the backend with this application_name (SET LOCAL) while it runs this call, e.g. a request ID to find the call in pg_stat_activity"
+    )]
+    #[serde(default)]
+    pub application_name: Option<String>,
+    #[schemars(
+        description = "Run this call as another role (SET LOCAL ROLE), with that role's privileges and row-level security. The connecting user must be a member of it; the connection reverts to its own role when the call ends"
+    )]
+    #[serde(default)]
+    pub run_as_role: Option<String>,
+    #[schemars(
+        description = "Run this call in the transaction with this ID from begin_transaction instead of on its own. Can't be combined with application_name, run_as_role or timeout_secs"
+    )]
+    #[serde(default)]
+    pub tx_id: Option<String>,
+    #[schemars(
+        description = "Cancel the statement after this many seconds (statement_timeout). A cancelled write is rolled back and fails with a query timeout error"
+    )]
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct CreateTableRequest {
+pub struct BeginTransactionRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
-    #[schemars(description = "Single SQL create table statement")]
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct QueryStreamRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "SELECT query whose rows fetch_cursor returns page by page")]
     pub query: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct DropTableRequest {
+pub struct FetchCursorRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Cursor ID returned by query_stream")]
+    pub cursor_id: String,
+    #[schemars(description = "Most rows to return, 1 to 10000. Defaults to 1000")]
+    #[serde(default)]
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CloseCursorRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Cursor ID returned by query_stream")]
+    pub cursor_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EndTransactionRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Transaction ID returned by begin_transaction")]
+    pub tx_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct EstimateImpactRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Single SQL UPDATE or DELETE statement to estimate. It is not executed"
+    )]
+    pub query: String,
+    #[schemars(description = "Number of matching rows to return as a sample (default 5, max 100)")]
+    #[serde(default)]
+    pub sample_size: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CopyFromFileRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
     #[schemars(
-        description = "Table name. Format: schema.table. If schema is not provided, it will use the current schema."
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
     )]
     pub table: String,
+    #[schemars(
+        description = "Path of the CSV file on the server, relative to the server's import directory"
+    )]
+    pub path: String,
+    #[schemars(description = "The first line of the file is a header and is skipped")]
+    #[serde(default)]
+    pub header: bool,
+    #[schemars(
+        description = "Table columns the CSV columns map to, in order. Defaults to all the columns of the table"
+    )]
+    #[serde(default)]
+    pub columns: Vec<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct CreateIndexRequest {
+pub struct CopyInRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
-    #[schemars(description = "SingleSQL create index statement")]
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(
+        description = "Table columns the CSV columns map to, in order. Defaults to all the columns of the table"
+    )]
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[schemars(
+        description = "The rows as CSV: one line per row, comma-separated, with double quotes around values containing commas, quotes or newlines. An unquoted empty value is NULL"
+    )]
+    pub csv_data: String,
+    #[schemars(
+        description = "Encoding of csv_data: 'none' (default) for plain CSV, or 'gzip' for the base64 of the gzip-compressed CSV, to send more rows in one message. Compressed data may expand to at most 64 MiB"
+    )]
+    #[serde(default)]
+    pub compression: Compression,
+    #[schemars(description = "The first line of csv_data is a header and is skipped")]
+    #[serde(default)]
+    pub header: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenerateTestDataRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "Number of rows to insert (max 100000)")]
+    pub row_count: u32,
+    #[schemars(
+        description = "Seed of the random values. The same seed generates the same rows; omit it for a random one, which is returned"
+    )]
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateTableRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Single SQL create table statement")]
     pub query: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct DropIndexRequest {
+pub struct CreateTableAsRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
-    #[schemars(description = "Index name")]
-    pub index: String,
+    #[schemars(
+        description = "Name of the table to create. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub dest: String,
+    #[schemars(description = "Single SELECT query whose result fills the new table")]
+    pub query: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct DescribeRequest {
+pub struct DropTableRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
-    #[schemars(description = "Table name")]
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
     pub table: String,
+    #[schemars(description = "Succeed without doing anything if the table doesn't exist")]
+    #[serde(default)]
+    pub if_exists: bool,
+    #[schemars(
+        description = "Also drop the objects that depend on the table, such as views and foreign key constraints"
+    )]
+    #[serde(default)]
+    pub cascade: bool,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct ListTablesRequest {
+pub struct DropOrderRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Schema name")]
+    pub schema: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DropAllTablesRequest {
     #[schemars(description = "Connection ID")]
     pub conn_id: String,
     #[schemars(description = "Schema name")]
     pub schema: String,
+    #[schemars(
+        description = "Must be true to actually drop the tables. All tables and their data in the schema will be lost"
+    )]
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SafeRenameTableRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "New table name, without schema")]
+    pub new_name: String,
+    #[schemars(
+        description = "Rename even if functions reference the table by name and will break"
+    )]
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RenameTableRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "New table name, without schema")]
+    pub new_name: String,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct CreateSchemaRequest {
-    #[schemars(description = "Connection ID")]
-    pub conn_id: String,
-    #[schemars(description = "Schema name")]
-    pub name: String,
-}
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RenameColumnRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "Column to rename")]
+    pub column: String,
+    #[schemars(description = "New column name")]
+    pub new_name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddColumnSafeRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "Name of the new column")]
+    pub column: String,
+    #[schemars(description = "Column type, e.g. text, integer, numeric(10,2), timestamptz")]
+    #[serde(rename = "type")]
+    pub data_type: String,
+    #[schemars(
+        description = "Default as a SQL expression, e.g. 'active' (quoted), 0, now() or gen_random_uuid()"
+    )]
+    pub default: String,
+    #[schemars(
+        description = "Rows updated per batch when existing rows have to be backfilled (default 10000)"
+    )]
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GenerateAlterDdlRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(
+        description = "Every column the table should have, in order. Existing columns left out are dropped; a column without default has none"
+    )]
+    pub columns: Vec<pg::TargetColumn>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyMigrationVerifiedRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Migration SQL: one or more statements separated by semicolons, without BEGIN, COMMIT or ROLLBACK"
+    )]
+    pub sql: String,
+    #[schemars(
+        description = "SELECT run after the migration, in the same transaction, to verify it"
+    )]
+    pub check_query: String,
+    #[schemars(
+        description = "Rows the check query must return, as a JSON array of row objects, e.g. [{\"missing\": 0}]. If omitted, the check passes when it returns at least one row and every value of the first row is truthy (not null, false, 0 or empty)"
+    )]
+    #[serde(default)]
+    pub expected: Option<serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateIndexRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "SingleSQL create index statement")]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecuteDdlRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Single SQL statement of an allowed kind, e.g. ALTER TABLE orders ADD COLUMN note text"
+    )]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "SQL statements run in order, one per element, e.g. [\"INSERT INTO accounts (id) VALUES (1)\", \"UPDATE totals SET n = n + 1\"]"
+    )]
+    pub statements: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DropIndexRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Index name. Format: schema.index. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub index: String,
+    #[schemars(description = "Succeed without doing anything if the index doesn't exist")]
+    #[serde(default)]
+    pub if_exists: bool,
+    #[schemars(
+        description = "Also drop the objects that depend on the index, such as foreign keys that reference a unique index"
+    )]
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct UnusedIndexesRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListRolesRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RoleMembershipsRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListTriggersRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DumpTableDdlRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListIndexesRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckReferentialIntegrityRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Table whose foreign keys to check")]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CountEstimateRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(
+        description = "Run an exact COUNT(*) instead of returning the estimate. Slow on large tables."
+    )]
+    #[serde(default)]
+    pub exact: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TableChecksumRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(
+        description = "Columns to order the rows by before hashing. Defaults to the primary key; required for tables without one"
+    )]
+    #[serde(default)]
+    pub order_by: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ColumnHistogramRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(description = "Numeric, date or timestamp column to bucket")]
+    pub column: String,
+    #[schemars(description = "Number of equal-width buckets, from 1 to 1000")]
+    pub buckets: u32,
+    #[schemars(
+        description = "Percentage of the table's pages to sample (TABLESAMPLE SYSTEM), between 0 and 100. Reads the whole table when omitted"
+    )]
+    #[serde(default)]
+    pub sample_percent: Option<f64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetAutovacuumSettingsRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SetAutovacuumSettingsRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+    #[schemars(
+        description = "Storage parameters to set, e.g. { \"autovacuum_vacuum_scale_factor\": 0.01, \"autovacuum_enabled\": true }. A null value resets the parameter to the server setting. Allowed: autovacuum_enabled, autovacuum_vacuum_threshold, autovacuum_vacuum_scale_factor, autovacuum_vacuum_insert_threshold, autovacuum_vacuum_insert_scale_factor, autovacuum_analyze_threshold, autovacuum_analyze_scale_factor, autovacuum_vacuum_cost_delay, autovacuum_vacuum_cost_limit, autovacuum_freeze_min_age, autovacuum_freeze_max_age, autovacuum_freeze_table_age, autovacuum_multixact_freeze_min_age, autovacuum_multixact_freeze_max_age, autovacuum_multixact_freeze_table_age, log_autovacuum_min_duration"
+    )]
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AlterDatabaseSettingRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Setting name. Allowed: search_path, timezone, datestyle, intervalstyle, statement_timeout, lock_timeout, idle_in_transaction_session_timeout, default_transaction_isolation, default_transaction_read_only, work_mem, maintenance_work_mem, random_page_cost, effective_cache_size, jit"
+    )]
+    pub name: String,
+    #[schemars(
+        description = "New default, e.g. 'UTC' or '30s'. For search_path, a comma separated list of schemas. Omit to reset the setting to the server default"
+    )]
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ReplicationStatusRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WalStatusRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WraparoundStatusRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListPreparedTransactionsRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RollbackPreparedRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Global identifier of the prepared transaction, as listed by list_prepared_transactions"
+    )]
+    pub gid: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TableJsonSchemaRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Table name. Format: schema.table. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub table: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListTablesRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Schema name")]
+    pub schema: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SelfDiagnosticsRequest {
+    #[schemars(description = "Connection ID to report. Every connection if not set")]
+    #[serde(default)]
+    pub conn_id: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListSchemasRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Also list Postgres' own schemas: pg_catalog, information_schema, pg_toast and the temporary ones"
+    )]
+    #[serde(default)]
+    pub include_system: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListMaterializedViewsRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Schema name")]
+    pub schema: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateSchemaRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Schema name")]
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DropSchemaRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Schema name")]
+    pub name: String,
+    #[schemars(
+        description = "Also drop the tables, views, functions and other objects in the schema. Without it, dropping a schema that isn't empty fails"
+    )]
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CreateTypeRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(description = "Single SQL create type statement")]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AlterTypeRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Single SQL alter type statement, e.g. ALTER TYPE mood ADD VALUE 'calm' AFTER 'sad'"
+    )]
+    pub query: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DropTypeRequest {
+    #[schemars(description = "Connection ID")]
+    pub conn_id: String,
+    #[schemars(
+        description = "Type name. Format: schema.type. If schema is not provided, it will use the server's default schema (public unless configured otherwise)."
+    )]
+    pub type_name: String,
+    #[schemars(
+        description = "Also drop the objects that depend on the type, such as columns and functions using it"
+    )]
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+// Helper function to map PgMcpError to McpError. Errors the caller can fix,
+// such as an unknown ID or a rejected statement, are invalid params
+// (-32602); calls refused by the server's policy (rate and connection
+// limits, --disable-ddl, tool roles) are invalid requests (-32600); the
+// others are internal errors (-32603). Each carries its kind and fields in
+// `data`, so clients needn't parse the message.
+fn map_pg_error(e: PgMcpError) -> McpError {
+    match e {
+        PgMcpError::ConnectionNotFound(id) => McpError::invalid_params(
+            format!("Invalid Argument: Connection not found for ID: {}", id),
+            Some(serde_json::json!({
+                "kind": "connection_not_found",
+                "conn_id": id,
+            })),
+        ),
+        PgMcpError::SchemaNotFound(schema) => McpError::invalid_params(
+            format!("Invalid Argument: Schema not found: {}", schema),
+            Some(serde_json::json!({
+                "kind": "schema_not_found",
+                "schema": schema,
+            })),
+        ),
+        PgMcpError::TransactionNotFound(tx_id) => McpError::invalid_params(
+            format!(
+                "Invalid Argument: Transaction not found: {}. It was committed, rolled back, or rolled back after being idle too long",
+                tx_id
+            ),
+            Some(serde_json::json!({
+                "kind": "transaction_not_found",
+                "tx_id": tx_id,
+            })),
+        ),
+        PgMcpError::CursorNotFound(cursor_id) => McpError::invalid_params(
+            format!(
+                "Invalid Argument: Cursor not found: {}. It was closed, or closed after being idle too long",
+                cursor_id
+            ),
+            Some(serde_json::json!({
+                "kind": "cursor_not_found",
+                "cursor_id": cursor_id,
+            })),
+        ),
+        PgMcpError::TableNotFound {
+            table,
+            did_you_mean,
+        } => {
+            let hint = match did_you_mean.as_slice() {
+                [] => String::new(),
+                [name] => format!(" Did you mean {}?", name),
+                names => format!(" Did you mean one of {}?", names.join(", ")),
+            };
+            McpError::invalid_params(
+                format!("Invalid Argument: Table not found: {}.{}", table, hint),
+                Some(serde_json::json!({
+                    "kind": "table_not_found",
+                    "table": table,
+                    "did_you_mean": did_you_mean,
+                })),
+            )
+        }
+        PgMcpError::ValidationFailed {
+            kind,
+            query,
+            details,
+        } => McpError::invalid_params(
+            format!(
+                "Invalid Argument: SQL validation failed for query '{}': {} - {}",
+                query, kind, details
+            ),
+            Some(serde_json::json!({
+                "kind": "validation_failed",
+                "reason": validation_reason(&kind),
+                "query": query,
+                "details": details,
+            })),
+        ),
+        PgMcpError::DatabaseError {
+            operation,
+            underlying,
+        } => McpError::internal_error(
+            format!("Database operation '{}' failed: {}", operation, underlying),
+            Some(serde_json::json!({
+                "kind": "database_error",
+                "operation": operation,
+                "details": underlying,
+            })),
+        ),
+        PgMcpError::PoolExhausted {
+            operation,
+            acquire_timeout_ms,
+            retry_after_ms,
+        } => McpError::internal_error(
+            format!(
+                "Connection pool exhausted: operation '{}' got no connection within {}ms, retry after {}ms",
+                operation, acquire_timeout_ms, retry_after_ms
+            ),
+            Some(serde_json::json!({
+                "kind": "pool_exhausted",
+                "operation": operation,
+                "acquire_timeout_ms": acquire_timeout_ms,
+                "retry_after_ms": retry_after_ms,
+            })),
+        ),
+        PgMcpError::QueryTimeout {
+            operation,
+            timeout_ms,
+        } => McpError::internal_error(
+            format!(
+                "Query timed out: operation '{}' was cancelled after {}ms. Narrow the query (e.g. a more selective WHERE) or retry with a longer timeout_secs",
+                operation, timeout_ms
+            ),
+            Some(serde_json::json!({
+                "kind": "query_timeout",
+                "operation": operation,
+                "timeout_ms": timeout_ms,
+            })),
+        ),
+        PgMcpError::SerializationError(se) => McpError::internal_error(
+            format!("Result serialization failed: {}", se),
+            Some(serde_json::json!({
+                "kind": "serialization_error",
+                "details": se.to_string(),
+            })),
+        ),
+        PgMcpError::ConnectionError(ce) => McpError::internal_error(
+            format!("Database connection failed: {}", ce),
+            Some(serde_json::json!({
+                "kind": "connection_error",
+                "details": ce,
+            })),
+        ),
+        PgMcpError::InternalError(ie) => McpError::internal_error(
+            format!("Internal error: {}", ie),
+            Some(serde_json::json!({
+                "kind": "internal_error",
+                "details": ie,
+            })),
+        ),
+        PgMcpError::ConnectionLimitReached { limit } => McpError::invalid_request(
+            format!(
+                "Connection limit reached for this session: {} connections are registered. Unregister one before registering another",
+                limit
+            ),
+            Some(serde_json::json!({
+                "kind": "connection_limit_reached",
+                "limit": limit,
+            })),
+        ),
+        PgMcpError::RateLimited { retry_after_ms } => McpError::invalid_request(
+            format!("Rate limited, retry after {}ms", retry_after_ms),
+            Some(serde_json::json!({
+                "kind": "rate_limited",
+                "retry_after_ms": retry_after_ms,
+            })),
+        ),
+        PgMcpError::DdlDisabled(tool) => McpError::invalid_request(
+            format!("DDL disabled: tool '{}' modifies the schema", tool),
+            Some(serde_json::json!({
+                "kind": "ddl_disabled",
+                "tool": tool,
+            })),
+        ),
+        PgMcpError::ToolNotAuthorized { tool, role } => McpError::invalid_request(
+            format!(
+                "Not authorized: role '{}' may not call tool '{}'",
+                role, tool
+            ),
+            Some(serde_json::json!({
+                "kind": "not_authorized",
+                "tool": tool,
+                "role": role,
+            })),
+        ),
+    }
+}
+
+// Why a statement or argument was rejected, in the `data` of the error.
+fn validation_reason(kind: &ValidationErrorKind) -> &'static str {
+    match kind {
+        ValidationErrorKind::InvalidStatementType { .. } => "invalid_statement_type",
+        ValidationErrorKind::ParseError => "parse_error",
+        ValidationErrorKind::Unsupported => "unsupported",
+        ValidationErrorKind::MultiTableWrite => "multi_table_write",
+        ValidationErrorKind::ReadOnly => "read_only",
+        ValidationErrorKind::InvalidArgument => "invalid_argument",
+        ValidationErrorKind::TableNotAllowed => "table_not_allowed",
+    }
+}
+
+/// Tools that change the schema and are blocked by `--disable-ddl`.
+const DDL_TOOLS: &[&str] = &[
+    "create_table",
+    "create_table_as",
+    "drop_table",
+    "drop_all_tables",
+    "safe_rename_table",
+    "rename_table",
+    "rename_column",
+    "create_index",
+    "drop_index",
+    "execute_ddl",
+    "create_schema",
+    "drop_schema",
+    "create_type",
+    "alter_type",
+    "drop_type",
+    "set_autovacuum_settings",
+    "apply_migration_verified",
+    "alter_database_setting",
+    "add_column_safe",
+];
+
+/// Tools a role must be allowed to call to list and read table resources,
+/// which show the same columns and sample rows.
+const RESOURCE_TOOLS: &[&str] = &["describe", "query"];
+
+const RESOURCE_URI_SCHEME: &str = "pg://";
+
+/// Bytes escaped in a resource URI segment: all but the unreserved ones, so
+/// names with `/`, `%`, spaces or quotes round-trip.
+const RESOURCE_URI_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// Tables are exposed as resources with URIs like pg://<conn_id>/<schema>/<table>,
+// each segment percent-encoded.
+fn table_resource_uri(conn_id: &str, schema: &str, table: &str) -> String {
+    format!(
+        "{}{}/{}/{}",
+        RESOURCE_URI_SCHEME,
+        utf8_percent_encode(conn_id, RESOURCE_URI_SEGMENT),
+        utf8_percent_encode(schema, RESOURCE_URI_SEGMENT),
+        utf8_percent_encode(table, RESOURCE_URI_SEGMENT)
+    )
+}
+
+fn parse_table_resource_uri(uri: &str) -> Option<(String, String, String)> {
+    let mut parts = uri
+        .strip_prefix(RESOURCE_URI_SCHEME)?
+        .split('/')
+        .map(|part| {
+            let part = percent_decode_str(part).decode_utf8().ok()?;
+            (!part.is_empty()).then(|| part.into_owned())
+        });
+    let conn_id = parts.next()??;
+    let schema = parts.next()??;
+    let table = parts.next()??;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((conn_id, schema, table))
+}
+
+// Every tool takes an optional `intent`, which only ends up in logs, so it
+// is added to the schemas here rather than to each request struct.
+fn add_intent_property(schema: &mut serde_json::Map<String, serde_json::Value>) {
+    let properties = schema
+        .entry("properties")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(properties) = properties.as_object_mut() {
+        properties.insert(
+            "intent".to_string(),
+            serde_json::json!({
+                "description": "Why the call is made, in plain language. Recorded in the logs and the workload file next to the SQL; it doesn't affect execution",
+                "type": ["string", "null"],
+            }),
+        );
+    }
+}
+
+// The call's `intent` argument, made safe for line-based logs: control
+// characters such as newlines become spaces and long intents are cut.
+fn call_intent(request: &CallToolRequestParam) -> Option<String> {
+    let intent = request.arguments.as_ref()?.get("intent")?.as_str()?;
+    let intent: String = intent
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_INTENT_CHARS)
+        .collect();
+    let intent = intent.trim();
+    (!intent.is_empty()).then(|| intent.to_string())
+}
+
+#[tool(tool_box)]
+impl PgMcp {
+    pub fn new() -> Self {
+        Self::with_config(PgMcpConfig::default())
+    }
+
+    pub fn with_config(config: PgMcpConfig) -> Self {
+        let rate_limiter = config
+            .rate_limit
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        let mut conns = Conns::new();
+        conns.rate_limiter = config
+            .rate_limit
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        conns.write_tx = config.wrap_writes_in_tx;
+        conns.single_table_writes = config.single_table_writes;
+        if let Some(default_schema) = config.default_schema {
+            conns.default_schema = default_schema;
+        }
+        conns.import_dir = config.import_dir;
+        conns.echo_effective_sql = config.echo_effective_sql;
+        conns.serialization_mode = config.serialization_mode;
+        conns.default_row_limit = match config.default_row_limit.unwrap_or(DEFAULT_ROW_LIMIT) {
+            0 => None,
+            limit => Some(limit),
+        };
+        let tx_idle_timeout = config.tx_idle_timeout.unwrap_or(DEFAULT_TX_IDLE_TIMEOUT);
+        conns.transactions = Transactions::new(tx_idle_timeout);
+        conns.cursors = Transactions::new(tx_idle_timeout);
+        conns.max_conns_per_session = config.max_conns_per_session;
+        conns.registry = config
+            .registry_file
+            .map(|path| Arc::new(Registry::new(path)));
+        if let Some(ddl_allowlist) = config.ddl_allowlist {
+            conns.ddl_allowlist = ddl_allowlist;
+        }
+        conns.max_statement_timeout = config.max_statement_timeout;
+        conns.conn_idle_ttl = Some(config.conn_idle_ttl.unwrap_or(DEFAULT_CONN_IDLE_TTL))
+            .filter(|ttl| !ttl.is_zero());
+        Self {
+            conns,
+            rate_limiter,
+            disable_ddl: config.disable_ddl,
+            recorder: config.record_workload,
+            role: None,
+        }
+    }
+
+    /// A clone for another session: it shares the registered connections
+    /// and their rate limits, but counts the ones it registers against
+    /// --max-conns-per-session, and its calls without a connection against
+    /// --rate-limit, on its own.
+    pub fn for_new_session(&self) -> Self {
+        Self {
+            conns: self.conns.for_new_session(),
+            rate_limiter: self
+                .rate_limiter
+                .as_ref()
+                .map(|limiter| Arc::new(limiter.for_new_session())),
+            ..self.clone()
+        }
+    }
+
+    /// Re-register the connections saved with --registry-file, logging and
+    /// dropping the ones that fail to connect.
+    pub async fn restore_connections(&self) {
+        self.conns.restore_registry().await;
+    }
+
+    /// Limit the session to the tools of `role`, as authenticated by the SSE
+    /// server. `None` allows every tool.
+    pub fn with_role(mut self, role: Option<ToolRole>) -> Self {
+        self.role = role;
+        self
+    }
+
+    // Run a statement-executing call, appending it to the workload file when
+    // recording is enabled.
+    async fn recorded<T>(
+        &self,
+        tool: WorkloadTool,
+        query: &str,
+        params: &[serde_json::Value],
+        call: impl Future<Output = Result<T, PgMcpError>>,
+    ) -> Result<T, PgMcpError> {
+        let Some(recorder) = &self.recorder else {
+            return call.await;
+        };
+        let started = SystemTime::now();
+        let result = call.await;
+        let intent = CALL_INTENT.try_with(Clone::clone).ok().flatten();
+        recorder.record(
+            tool,
+            query,
+            params,
+            intent.as_deref(),
+            started,
+            result.is_ok(),
+        );
+        result
+    }
+
+    fn check_ddl_allowed(&self, request: &CallToolRequestParam) -> Result<(), PgMcpError> {
+        if self.disable_ddl && DDL_TOOLS.contains(&request.name.as_ref()) {
+            return Err(PgMcpError::DdlDisabled(request.name.to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_tool_authorized(&self, request: &CallToolRequestParam) -> Result<(), PgMcpError> {
+        match &self.role {
+            Some(role) if !role.allows(&request.name) => Err(PgMcpError::ToolNotAuthorized {
+                tool: request.name.to_string(),
+                role: role.name.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Table resources show columns and sample rows, so they are only
+    // available to roles that may call the tools reading those.
+    fn check_resources_authorized(&self) -> Result<(), PgMcpError> {
+        let Some(role) = &self.role else {
+            return Ok(());
+        };
+        match RESOURCE_TOOLS.iter().find(|tool| !role.allows(tool)) {
+            Some(tool) => Err(PgMcpError::ToolNotAuthorized {
+                tool: tool.to_string(),
+                role: role.name.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // Rate limits are tracked per registered conn_id, in a bucket shared by
+    // every session using the connection. Other calls, including those
+    // naming an unknown conn_id, share one bucket per session.
+    fn check_rate_limit(&self, conn_id: Option<&str>) -> Result<(), PgMcpError> {
+        let Some(limiter) = &self.rate_limiter else {
+            return Ok(());
+        };
+        conn_id
+            .and_then(|id| self.conns.check_rate_limit(id))
+            .unwrap_or_else(|| limiter.check(""))
+            .map_err(|retry_after| PgMcpError::RateLimited {
+                retry_after_ms: retry_after.as_millis().max(1) as u64,
+            })
+    }
+
+    #[tool(
+        description = "Register a new Postgres connection. Returns the connection ID, followed by a JSON object with connect_timings (dns_ms, tcp_connect_ms, handshake_ms, total_ms, tls) to tell which phase of a slow registration is slow, and in_recovery/warning if the database is a standby in recovery"
+    )]
+    async fn register(
+        &self,
+        #[tool(aggr)] req: RegisterRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let registration = self
+            .conns
+            .register(req.conn_str, req.options.conn_options())
+            .await
+            .map_err(map_pg_error)?;
+        Ok(req.options.registered(registration))
+    }
+
+    #[tool(
+        description = "Register a new Postgres connection whose connection string is read from an environment variable of the server, e.g. DATABASE_URL, so the password never passes through the client. Returns the same as register"
+    )]
+    async fn register_env(
+        &self,
+        #[tool(aggr)] req: RegisterEnvRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let registration = self
+            .conns
+            .register_env(&req.env_var, req.options.conn_options())
+            .await
+            .map_err(map_pg_error)?;
+        Ok(req.options.registered(registration))
+    }
+
+    #[tool(
+        description = "Export the registered connections (ID, connection string without password, and options) to restore them later with import_connections"
+    )]
+    async fn export_connections(&self) -> Result<CallToolResult, McpError> {
+        let result = self.conns.export_connections().map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Report the connection pool of every registered connection, or of conn_id, for monitoring this server: { connection_count, connections: [{ conn_id, max_connections, size, idle, in_use, last_used_at_ms, idle_ms, read_only }] }. connection_count counts every registered connection. last_used_at_ms is the Unix time in milliseconds a pooled connection was last returned, and idle_ms how long ago that was"
+    )]
+    async fn self_diagnostics(
+        &self,
+        #[tool(aggr)] req: SelfDiagnosticsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .self_diagnostics(req.conn_id.as_deref())
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Re-register connections exported by export_connections under their original IDs. Returns the status of each: registered, skipped (ID already registered) or failed with an error"
+    )]
+    async fn import_connections(
+        &self,
+        #[tool(aggr)] req: ImportConnectionsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .import_connections(req.connections, &req.passwords)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Unregister a Postgres connection")]
+    async fn unregister(
+        &self,
+        #[tool(aggr)] req: UnregisterRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.conns.unregister(req.conn_id).map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(
+            "success".to_string(),
+        )]))
+    }
+
+    #[tool(description = "Execute a SELECT query")]
+    async fn query(&self, #[tool(aggr)] req: QueryRequest) -> Result<CallToolResult, McpError> {
+        let options = QueryOptions {
+            params: req.params,
+            param_types: req.param_types,
+            format: if req.include_metadata {
+                QueryFormat::Typed
+            } else {
+                req.format
+            },
+            application_name: req.application_name,
+            run_as_role: req.run_as_role,
+            with_row_numbers: req.with_row_numbers,
+            transpose: req.transpose,
+            timeout: req.timeout_secs.map(Duration::from_secs),
+            tx_id: req.tx_id,
+            limit: req.limit,
+            offset: req.offset,
+        };
+        let result = self
+            .recorded(
+                WorkloadTool::Query,
+                &req.query,
+                &options.params,
+                self.conns.query(&req.conn_id, &req.query, &options),
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Execute a SELECT query and return one page of rows using keyset pagination. Returns { rows, next_cursor }; pass next_cursor back to fetch the following page. It is null on the last page"
+    )]
+    async fn paginate_query(
+        &self,
+        #[tool(aggr)] req: PaginateQueryRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .paginate_query(
+                &req.conn_id,
+                &req.query,
+                &req.order_by,
+                req.page_size,
+                req.cursor.as_deref(),
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Parse SQL without a database connection and return, per statement, its kind, referenced tables and columns, and the full parser AST as JSON. Invalid SQL returns an error with the message, line and column"
+    )]
+    async fn parse_sql(
+        &self,
+        #[tool(aggr)] req: ParseSqlRequest,
+    ) -> Result<CallToolResult, McpError> {
+        match pg::parse_sql(&req.query) {
+            Ok(result) => Ok(CallToolResult::success(vec![Content::text(result)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(
+                serde_json::json!({ "error": e }).to_string(),
+            )])),
+        }
+    }
+
+    #[tool(
+        description = "Classify a single SQL statement without a database connection, to decide whether it is safe to retry or cache. Returns its kind, is_read_only, is_idempotent_guess (e.g. INSERT without ON CONFLICT or UPDATE SET n = n + 1 are not idempotent) and targets: the objects it modifies, or the relations it reads if read-only"
+    )]
+    async fn classify_statement(
+        &self,
+        #[tool(aggr)] req: ClassifyStatementRequest,
+    ) -> Result<CallToolResult, McpError> {
+        match pg::classify_statement(&req.query) {
+            Ok(result) => Ok(CallToolResult::success(vec![Content::text(result)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(
+                serde_json::json!({ "error": e }).to_string(),
+            )])),
+        }
+    }
+
+    #[tool(
+        description = "Describe the result columns (name, type, nullable) of a SELECT query without executing it"
+    )]
+    async fn describe_query(
+        &self,
+        #[tool(aggr)] req: DescribeQueryRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .describe_query(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Check a data-quality invariant: run a SELECT returning a single integer (e.g. a COUNT) and compare it to an expected value with eq, gte or lte. Returns { passed, actual, expected, comparison }; a failed check is not an error. Fails if the query doesn't return exactly one row with one integer column"
+    )]
+    async fn assert_count(
+        &self,
+        #[tool(aggr)] req: AssertCountRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .assert_count(&req.conn_id, &req.query, req.expected, req.comparison)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Return the query plan of a SELECT, INSERT, UPDATE or DELETE as EXPLAIN (FORMAT JSON) output. With analyze, the statement is executed to add actual rows and timings, in a transaction that is rolled back so writes don't persist"
+    )]
+    async fn explain(&self, #[tool(aggr)] req: ExplainRequest) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .explain(&req.conn_id, &req.query, req.analyze)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Estimate the memory needed by the sorts and hashes of a SELECT from its plan (EXPLAIN, not executed) and flag those likely to spill to disk because they exceed work_mem. Returns { work_mem, work_mem_bytes, hash_mem_multiplier, nodes: [{ node_type, estimated_rows, estimated_bytes, limit_bytes, likely_spills }], hint }, where hint suggests a session work_mem when a node spills"
+    )]
+    async fn estimate_query_memory(
+        &self,
+        #[tool(aggr)] req: EstimateQueryMemoryRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .estimate_query_memory(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Execute an INSERT statement. Returns { rows_affected, table }, or with a RETURNING clause the returned rows as a JSON array"
+    )]
+    async fn insert(&self, #[tool(aggr)] req: InsertRequest) -> Result<CallToolResult, McpError> {
+        let options = WriteOptions {
+            application_name: req.application_name,
+            run_as_role: req.run_as_role,
+            params: req.params,
+            param_types: req.param_types,
+            tx_id: req.tx_id,
+            timeout: req.timeout_secs.map(Duration::from_secs),
+        };
+        let result = if req.continue_on_error {
+            self.recorded(
+                WorkloadTool::InsertContinueOnError,
+                &req.query,
+                &options.params,
+                self.conns
+                    .insert_continue_on_error(&req.conn_id, &req.query, &options),
+            )
+            .await
+        } else {
+            self.recorded(
+                WorkloadTool::Insert,
+                &req.query,
+                &options.params,
+                self.conns.insert(&req.conn_id, &req.query, &options),
+            )
+            .await
+        }
+        .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Execute an UPDATE statement. Returns { rows_affected, table }")]
+    async fn update(&self, #[tool(aggr)] req: UpdateRequest) -> Result<CallToolResult, McpError> {
+        let options = WriteOptions {
+            application_name: req.application_name,
+            run_as_role: req.run_as_role,
+            tx_id: req.tx_id,
+            timeout: req.timeout_secs.map(Duration::from_secs),
+            ..Default::default()
+        };
+        let result = self
+            .recorded(
+                WorkloadTool::Update,
+                &req.query,
+                &[],
+                self.conns.update(&req.conn_id, &req.query, &options),
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Delete rows from a table. Returns { rows_affected, table }")]
+    async fn delete(&self, #[tool(aggr)] req: DeleteRequest) -> Result<CallToolResult, McpError> {
+        let options = WriteOptions {
+            application_name: req.application_name,
+            run_as_role: req.run_as_role,
+            tx_id: req.tx_id,
+            timeout: req.timeout_secs.map(Duration::from_secs),
+            ..Default::default()
+        };
+        let result = self
+            .recorded(
+                WorkloadTool::Delete,
+                &req.query,
+                &[],
+                self.conns.delete(&req.conn_id, &req.query, &options),
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Open a transaction for several query, insert, update and delete calls to run atomically: pass the returned tx_id to them, then commit_transaction or rollback_transaction. Returns { tx_id, idle_timeout_secs }. The transaction holds a pooled connection and its locks until it ends, and is rolled back once idle for idle_timeout_secs. A failed statement aborts it: roll it back and start over"
+    )]
+    async fn begin_transaction(
+        &self,
+        #[tool(aggr)] req: BeginTransactionRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .begin_transaction(&req.conn_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Commit a transaction opened with begin_transaction. Fails, after rolling it back, if one of its statements failed. Returns { tx_id, status }"
+    )]
+    async fn commit_transaction(
+        &self,
+        #[tool(aggr)] req: EndTransactionRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .commit_transaction(&req.conn_id, &req.tx_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Roll back a transaction opened with begin_transaction. Returns { tx_id, status }"
+    )]
+    async fn rollback_transaction(
+        &self,
+        #[tool(aggr)] req: EndTransactionRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .rollback_transaction(&req.conn_id, &req.tx_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Open a server-side cursor over a SELECT to read a large result in pages with fetch_cursor, without loading it all at once. The cursor holds a pooled connection until close_cursor, and is closed after being idle as long as a transaction. Returns { cursor_id, idle_timeout_secs }"
+    )]
+    async fn query_stream(
+        &self,
+        #[tool(aggr)] req: QueryStreamRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .query_stream(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Fetch the next rows of a cursor opened with query_stream. Returns { row_count, rows, done }, where done means no rows are left; the cursor stays open until close_cursor"
+    )]
+    async fn fetch_cursor(
+        &self,
+        #[tool(aggr)] req: FetchCursorRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .fetch_cursor(&req.conn_id, &req.cursor_id, req.count)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Close a cursor opened with query_stream. Returns { cursor_id, status }")]
+    async fn close_cursor(
+        &self,
+        #[tool(aggr)] req: CloseCursorRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .close_cursor(&req.conn_id, &req.cursor_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Preview an UPDATE or DELETE without running it: counts the rows its WHERE clause (and FROM/USING joins) matches and returns a sample of them, in a read-only transaction. Returns { statement_type, table, rows, sample, count_query, warning }, where warning is set if there is no WHERE clause. Use it to confirm a destructive statement before running it"
+    )]
+    async fn estimate_impact(
+        &self,
+        #[tool(aggr)] req: EstimateImpactRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .estimate_impact(&req.conn_id, &req.query, req.sample_size)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Load a large CSV file from the server's import directory into a table with COPY, streaming it instead of passing the data in the request. Only available if the server was started with --import-dir. Returns { table, path, rows }"
+    )]
+    async fn copy_from_file(
+        &self,
+        #[tool(aggr)] req: CopyFromFileRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let options = CopyOptions {
+            header: req.header,
+            columns: req.columns,
+        };
+        let result = self
+            .conns
+            .copy_from_file(&req.conn_id, &req.table, &req.path, &options)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Bulk-load many rows into a table from CSV data with COPY, much faster than insert for thousands of rows. The load is all or nothing: a malformed row or a value of the wrong type fails the whole call. Returns { table, rows }"
+    )]
+    async fn copy_in(&self, #[tool(aggr)] req: CopyInRequest) -> Result<CallToolResult, McpError> {
+        let options = CopyOptions {
+            header: req.header,
+            columns: req.columns,
+        };
+        let result = self
+            .conns
+            .copy_in(
+                &req.conn_id,
+                &req.table,
+                &req.csv_data,
+                req.compression,
+                &options,
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Insert rows of random test data into a table. Values match each column's type (numbers, text, uuid, dates and timestamps, enums, json, ...) and foreign keys reference existing rows; nullable columns are NULL one time in ten. Identity, generated and serial columns are left to Postgres, and unsupported types to their default. Returns { table, rows, seed, columns, skipped }"
+    )]
+    async fn generate_test_data(
+        &self,
+        #[tool(aggr)] req: GenerateTestDataRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .generate_test_data(&req.conn_id, &req.table, req.row_count, req.seed)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Create a new table")]
+    async fn create_table(
+        &self,
+        #[tool(aggr)] req: CreateTableRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .create_table(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Create a table from the result of a SELECT (CREATE TABLE dest AS query). The columns take their names and types from the query. Returns { table, row_count }"
+    )]
+    async fn create_table_as(
+        &self,
+        #[tool(aggr)] req: CreateTableAsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .create_table_as(&req.conn_id, &req.dest, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Drop a table")]
+    async fn drop_table(
+        &self,
+        #[tool(aggr)] req: DropTableRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .drop_table(
+                &req.conn_id,
+                &req.table,
+                DropOptions {
+                    if_exists: req.if_exists,
+                    cascade: req.cascade,
+                },
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Rename a table after checking its dependents. Returns { renamed, functions, views }. Views follow the rename automatically; functions that reference the table by name would break, so the rename is refused (renamed: false) unless force is true"
+    )]
+    async fn safe_rename_table(
+        &self,
+        #[tool(aggr)] req: SafeRenameTableRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .safe_rename_table(&req.conn_id, &req.table, &req.new_name, req.force)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Rename a table with ALTER TABLE ... RENAME TO, keeping its schema. Views and foreign keys follow the rename; functions and queries that use the old name break, see safe_rename_table to check for those first. Returns { table, new_name }"
+    )]
+    async fn rename_table(
+        &self,
+        #[tool(aggr)] req: RenameTableRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .rename_table(&req.conn_id, &req.table, &req.new_name)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Rename a column of a table. Views and indexes follow the rename; functions and queries that use the old name break. Returns { table, column, new_name }"
+    )]
+    async fn rename_column(
+        &self,
+        #[tool(aggr)] req: RenameColumnRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .rename_column(&req.conn_id, &req.table, &req.column, &req.new_name)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Add a NOT NULL column with a default to a possibly large table without holding a long lock. A constant default on Postgres 11+ is added in one instant step; otherwise the column is added as nullable, the default set, existing rows backfilled in batches and NOT NULL added through a validated CHECK constraint. Returns { strategy, steps: [{ step, sql, rows_affected, duration_ms }] }"
+    )]
+    async fn add_column_safe(
+        &self,
+        #[tool(aggr)] req: AddColumnSafeRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .add_column_safe(
+                &req.conn_id,
+                &req.table,
+                &req.column,
+                &req.data_type,
+                &req.default,
+                req.batch_size,
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Diff the columns of a table against a target column list and return the ALTER TABLE ADD/ALTER/DROP COLUMN statements that get there, without running them: { table, statements, script, warnings }. Types and defaults are compared as Postgres normalizes them; defaults and NOT NULL of identity and generated columns are left alone"
+    )]
+    async fn generate_alter_ddl(
+        &self,
+        #[tool(aggr)] req: GenerateAlterDdlRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .generate_alter_ddl(&req.conn_id, &req.table, &req.columns)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Return the tables of a schema in an order that drops them without violating foreign keys: { order, cyclic }. Tables in a foreign key cycle are listed in cyclic and must be dropped together"
+    )]
+    async fn drop_order(
+        &self,
+        #[tool(aggr)] req: DropOrderRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .drop_order(&req.conn_id, &req.schema)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Drop all tables of a schema in foreign key order, in a single transaction. Requires confirm: true"
+    )]
+    async fn drop_all_tables(
+        &self,
+        #[tool(aggr)] req: DropAllTablesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        if !req.confirm {
+            return Err(McpError::invalid_params(
+                "drop_all_tables drops every table in the schema, set confirm to true to proceed",
+                None,
+            ));
+        }
+        let result = self
+            .conns
+            .drop_all_tables(&req.conn_id, &req.schema)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Run a migration and a verification query in one transaction, committing only if the check passes. Otherwise the migration is rolled back. Returns { applied, check_result, expected }"
+    )]
+    async fn apply_migration_verified(
+        &self,
+        #[tool(aggr)] req: ApplyMigrationVerifiedRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .apply_migration_verified(
+                &req.conn_id,
+                &req.sql,
+                &req.check_query,
+                req.expected.as_ref(),
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Run a schema or privilege statement the other tools don't cover, such as ALTER TABLE, TRUNCATE, GRANT or CREATE EXTENSION. Only statement kinds on the server's DDL allowlist are accepted (by default Alter*, Truncate, Grant and CreateExtension). Returns { statement, rows_affected }"
+    )]
+    async fn execute_ddl(
+        &self,
+        #[tool(aggr)] req: ExecuteDdlRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .execute_ddl(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Run several statements in one transaction, all or nothing: they are committed only if every one succeeds, and the first failure rolls them all back. Accepts INSERT, UPDATE and DELETE, plus CREATE TABLE, INDEX, VIEW, TYPE or SCHEMA and the kinds on the DDL allowlist unless DDL is disabled. Errors name the index of the failed statement. Returns [{ statement, rows_affected }] in order"
+    )]
+    async fn batch(&self, #[tool(aggr)] req: BatchRequest) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .batch(&req.conn_id, &req.statements, !self.disable_ddl)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Create an index")]
+    async fn create_index(
+        &self,
+        #[tool(aggr)] req: CreateIndexRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .create_index(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(description = "Drop an index")]
+    async fn drop_index(
+        &self,
+        #[tool(aggr)] req: DropIndexRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .drop_index(
+                &req.conn_id,
+                &req.index,
+                DropOptions {
+                    if_exists: req.if_exists,
+                    cascade: req.cascade,
+                },
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "List indexes that have never been scanned (excluding primary key, unique and constraint indexes), with their sizes. Advisory: usage statistics reset on pg_stat_reset() and crash recovery"
+    )]
+    async fn unused_indexes(
+        &self,
+        #[tool(aggr)] req: UnusedIndexesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .unused_indexes(&req.conn_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "List the roles of the cluster from pg_roles with their attributes: superuser, login, createdb, createrole, inherit, replication, bypassrls, connection_limit and valid_until. Passwords are never returned. Servers that restrict access to the catalogs may require extra privileges"
+    )]
+    async fn list_roles(
+        &self,
+        #[tool(aggr)] req: ListRolesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_roles(&req.conn_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "List role memberships from pg_auth_members as { role, member, grantor, admin_option } edges: member is granted role. Servers that restrict access to the catalogs may require extra privileges"
+    )]
+    async fn role_memberships(
+        &self,
+        #[tool(aggr)] req: RoleMembershipsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .role_memberships(&req.conn_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
-pub struct CreateTypeRequest {
-    #[schemars(description = "Connection ID")]
-    pub conn_id: String,
-    #[schemars(description = "Single SQL create type statement")]
-    pub query: String,
-}
+    #[tool(
+        description = "List the triggers of a table with their timing (BEFORE/AFTER/INSTEAD OF), events (INSERT/UPDATE/DELETE/TRUNCATE), level (ROW/STATEMENT) and the function they invoke"
+    )]
+    async fn list_triggers(
+        &self,
+        #[tool(aggr)] req: ListTriggersRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_triggers(&req.conn_id, &req.table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
 
-// Helper function to map PgMcpError to McpError
-fn map_pg_error(e: PgMcpError) -> McpError {
-    match e {
-        PgMcpError::ConnectionNotFound(id) => McpError::internal_error(
-            format!("Invalid Argument: Connection not found for ID: {}", id),
-            None,
-        ),
-        PgMcpError::ValidationFailed {
-            kind,
-            query,
-            details,
-        } => McpError::internal_error(
-            format!(
-                "Invalid Argument: SQL validation failed for query '{}': {} - {}",
-                query, kind, details
-            ),
-            None,
-        ),
-        PgMcpError::DatabaseError {
-            operation,
-            underlying,
-        } => McpError::internal_error(
-            format!("Database operation '{}' failed: {}", operation, underlying),
-            None,
-        ),
-        PgMcpError::SerializationError(se) => {
-            McpError::internal_error(format!("Result serialization failed: {}", se), None)
-        }
-        PgMcpError::ConnectionError(ce) => {
-            McpError::internal_error(format!("Database connection failed: {}", ce), None)
-        }
-        PgMcpError::InternalError(ie) => {
-            McpError::internal_error(format!("Internal error: {}", ie), None)
-        }
+    #[tool(
+        description = "List the indexes of a table with their key columns or expressions, whether they are unique or the primary key, and their CREATE INDEX definition"
+    )]
+    async fn list_indexes(
+        &self,
+        #[tool(aggr)] req: ListIndexesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_indexes(&req.conn_id, &req.table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
     }
-}
 
-#[tool(tool_box)]
-impl PgMcp {
-    pub fn new() -> Self {
-        Self {
-            conns: Conns::new(),
-        }
+    #[tool(
+        description = "Find orphaned rows of a table: for each of its foreign keys, count the rows whose referenced parent row is missing, as constraints added NOT VALID or triggers disabled during a bulk load can let through. Returns { table, foreign_keys: [{ name, columns, referenced_table, referenced_columns, validated, orphans }], orphans }. Scans the table once per foreign key"
+    )]
+    async fn check_referential_integrity(
+        &self,
+        #[tool(aggr)] req: CheckReferentialIntegrityRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .check_referential_integrity(&req.conn_id, &req.table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Register a new Postgres connection")]
-    async fn register(
+    #[tool(
+        description = "Count the rows of a table. Returns a fast estimate from the planner statistics (pg_class.reltuples) with \"estimate\": true by default; pass exact to run COUNT(*) instead"
+    )]
+    async fn count_estimate(
         &self,
-        #[tool(aggr)] req: RegisterRequest,
+        #[tool(aggr)] req: CountEstimateRequest,
     ) -> Result<CallToolResult, McpError> {
-        let id = self
+        let result = self
             .conns
-            .register(req.conn_str)
+            .count_estimate(&req.conn_id, &req.table, req.exact)
             .await
             .map_err(map_pg_error)?;
-        Ok(CallToolResult::success(vec![Content::text(id)]))
+        Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Unregister a Postgres connection")]
-    async fn unregister(
+    #[tool(
+        description = "Compute an md5 checksum over all rows of a table, ordered by its primary key (or order_by), to check that two copies of a table are identical. Returns { table, order_by, row_count, checksum }. Reads and sorts the whole table, so it is slow on large tables"
+    )]
+    async fn table_checksum(
         &self,
-        #[tool(aggr)] req: UnregisterRequest,
+        #[tool(aggr)] req: TableChecksumRequest,
     ) -> Result<CallToolResult, McpError> {
-        self.conns.unregister(req.conn_id).map_err(map_pg_error)?;
-        Ok(CallToolResult::success(vec![Content::text(
-            "success".to_string(),
-        )]))
+        let result = self
+            .conns
+            .table_checksum(&req.conn_id, &req.table, &req.order_by)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Execute a SELECT query")]
-    async fn query(&self, #[tool(aggr)] req: QueryRequest) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "Build a histogram of a numeric, date or timestamp column: its range is split into equal-width buckets with width_bucket. Returns [{ bucket_low, bucket_high, count }], including empty buckets. Pass sample_percent to sample large tables"
+    )]
+    async fn column_histogram(
+        &self,
+        #[tool(aggr)] req: ColumnHistogramRequest,
+    ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .query(&req.conn_id, &req.query)
+            .column_histogram(
+                &req.conn_id,
+                &req.table,
+                &req.column,
+                req.buckets,
+                req.sample_percent,
+            )
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Execute an INSERT statement")]
-    async fn insert(&self, #[tool(aggr)] req: InsertRequest) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "Show the autovacuum settings of a table: for each autovacuum storage parameter, the value set on the table (table_value), the server setting (server_value) and the effective value, plus n_live_tup, n_dead_tup, last_autovacuum and last_autoanalyze"
+    )]
+    async fn get_autovacuum_settings(
+        &self,
+        #[tool(aggr)] req: GetAutovacuumSettingsRequest,
+    ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .insert(&req.conn_id, &req.query)
+            .get_autovacuum_settings(&req.conn_id, &req.table)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Execute an UPDATE statement")]
-    async fn update(&self, #[tool(aggr)] req: UpdateRequest) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "Tune autovacuum for a table by setting or resetting its autovacuum storage parameters with ALTER TABLE ... SET/RESET. Returns the settings after the change, as get_autovacuum_settings"
+    )]
+    async fn set_autovacuum_settings(
+        &self,
+        #[tool(aggr)] req: SetAutovacuumSettingsRequest,
+    ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .update(&req.conn_id, &req.query)
+            .set_autovacuum_settings(&req.conn_id, &req.table, &req.settings)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Delete a row from a table")]
-    async fn delete(&self, #[tool(aggr)] req: DeleteRequest) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "Set the default of a setting for the current database with ALTER DATABASE ... SET, or reset it if no value is given. Applies to new sessions only. Returns { database, settings } with the database-level settings after the change. Requires owning the database"
+    )]
+    async fn alter_database_setting(
+        &self,
+        #[tool(aggr)] req: AlterDatabaseSettingRequest,
+    ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .delete(&req.conn_id, &req.query)
+            .alter_database_setting(&req.conn_id, &req.name, req.value.as_deref())
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Create a new table")]
-    async fn create_table(
+    #[tool(
+        description = "Report replication lag. On a primary, returns each replica from pg_stat_replication with lag_bytes and lag_seconds; on a standby, returns the WAL receive/replay positions and lag"
+    )]
+    async fn replication_status(
         &self,
-        #[tool(aggr)] req: CreateTableRequest,
+        #[tool(aggr)] req: ReplicationStatusRequest,
     ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .create_table(&req.conn_id, &req.query)
+            .replication_status(&req.conn_id)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Drop a table")]
-    async fn drop_table(
+    #[tool(
+        description = "Report the WAL and checkpoint status: role, current_lsn, checkpoint_timeout, max_wal_size, checkpoint_stats (timed/requested checkpoints, write/sync time, buffers written) and the last checkpoint from pg_control_checkpoint() with bytes_since_checkpoint and seconds_since_checkpoint. checkpoint is null with a checkpoint_error when the role lacks the privilege to read it"
+    )]
+    async fn wal_status(
         &self,
-        #[tool(aggr)] req: DropTableRequest,
+        #[tool(aggr)] req: WalStatusRequest,
     ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .drop_table(&req.conn_id, &req.table)
+            .wal_status(&req.conn_id)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Create an index")]
-    async fn create_index(
+    #[tool(
+        description = "Report the transaction ID wraparound risk: for each database and the 20 tables with the oldest relfrozenxid, the XID age as a percentage of autovacuum_freeze_max_age and of the wraparound limit, with at_risk set once the age exceeds autovacuum_freeze_max_age. Also returns the oldest open transaction, which holds back freezing"
+    )]
+    async fn wraparound_status(
         &self,
-        #[tool(aggr)] req: CreateIndexRequest,
+        #[tool(aggr)] req: WraparoundStatusRequest,
     ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .create_index(&req.conn_id, &req.query)
+            .wraparound_status(&req.conn_id)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Drop an index")]
-    async fn drop_index(
+    #[tool(
+        description = "List the transactions prepared for two-phase commit (pg_prepared_xacts) that are still pending: gid, transaction, owner, database, prepared, age_seconds, xid_age and the number of locks they hold. Orphaned ones hold their locks and block vacuum until rolled back"
+    )]
+    async fn list_prepared_transactions(
         &self,
-        #[tool(aggr)] req: DropIndexRequest,
+        #[tool(aggr)] req: ListPreparedTransactionsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_prepared_transactions(&req.conn_id)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Roll back a pending prepared transaction with ROLLBACK PREPARED, releasing its locks. The gid must be listed by list_prepared_transactions for the connection's database. Returns { gid, owner, database, rolled_back }"
+    )]
+    async fn rollback_prepared(
+        &self,
+        #[tool(aggr)] req: RollbackPreparedRequest,
     ) -> Result<CallToolResult, McpError> {
         let result = self
             .conns
-            .drop_index(&req.conn_id, &req.index)
+            .rollback_prepared(&req.conn_id, &req.gid)
             .await
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "Describe a table")]
+    #[tool(
+        description = "Describe a table. Columns with is_generated = ALWAYS or identity_generation = ALWAYS cannot be inserted into"
+    )]
     async fn describe(
         &self,
         #[tool(aggr)] req: DescribeRequest,
@@ -299,7 +2438,54 @@ impl PgMcp {
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
-    #[tool(description = "List tables in a schema")]
+    #[tool(
+        description = "Reconstruct the DDL of a table as SQL: a CREATE TABLE with its columns, types, defaults, identity and generated columns, NOT NULL, primary key, unique, check, foreign key and exclusion constraints, followed by the CREATE INDEX of its other indexes. Run it on another database to clone the table's schema"
+    )]
+    async fn dump_table_ddl(
+        &self,
+        #[tool(aggr)] req: DumpTableDdlRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .dump_table_ddl(&req.conn_id, &req.table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Return a JSON Schema (draft 2020-12) describing a row of a table as returned by query: types and formats (uuid, date, date-time) per column, enum values, maxLength for varchar(n), nullable columns as [type, \"null\"], NOT NULL columns in required and generated columns as readOnly"
+    )]
+    async fn table_json_schema(
+        &self,
+        #[tool(aggr)] req: TableJsonSchemaRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .table_json_schema(&req.conn_id, &req.table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "List the schemas the connection's user can access, to find where tables live instead of assuming public: [{ name, owner }], sorted by name"
+    )]
+    async fn list_schemas(
+        &self,
+        #[tool(aggr)] req: ListSchemasRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_schemas(&req.conn_id, req.include_system)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "List tables in a schema. Returns [] for an empty schema and a \"Schema not found\" error if the schema does not exist"
+    )]
     async fn list_tables(
         &self,
         #[tool(aggr)] req: ListTablesRequest,
@@ -312,6 +2498,21 @@ impl PgMcp {
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
+    #[tool(
+        description = "List the materialized views of a schema: [{ name, populated, definition, last_refresh }]. last_refresh is the modification time of the view's data file, as Postgres doesn't record refreshes, and null without permission to read it. Returns [] for a schema without materialized views and a \"Schema not found\" error if the schema does not exist"
+    )]
+    async fn list_materialized_views(
+        &self,
+        #[tool(aggr)] req: ListMaterializedViewsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .list_materialized_views(&req.conn_id, &req.schema)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
     #[tool(description = "Create a new schema")]
     async fn create_schema(
         &self,
@@ -325,6 +2526,26 @@ impl PgMcp {
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
+    #[tool(description = "Drop a schema")]
+    async fn drop_schema(
+        &self,
+        #[tool(aggr)] req: DropSchemaRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .drop_schema(
+                &req.conn_id,
+                &req.name,
+                DropOptions {
+                    cascade: req.cascade,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
     #[tool(description = "Create a new type")]
     async fn create_type(
         &self,
@@ -337,17 +2558,188 @@ impl PgMcp {
             .map_err(map_pg_error)?;
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
+
+    #[tool(
+        description = "Alter a type, e.g. add a value to an enum with ALTER TYPE ... ADD VALUE. Runs in autocommit, outside any transaction, so an added value can be used right away"
+    )]
+    async fn alter_type(
+        &self,
+        #[tool(aggr)] req: AlterTypeRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .alter_type(&req.conn_id, &req.query)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
+
+    #[tool(
+        description = "Drop a type. Fails listing the dependent columns and functions unless cascade is set"
+    )]
+    async fn drop_type(
+        &self,
+        #[tool(aggr)] req: DropTypeRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let result = self
+            .conns
+            .drop_type(
+                &req.conn_id,
+                &req.type_name,
+                DropOptions {
+                    cascade: req.cascade,
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(map_pg_error)?;
+        Ok(CallToolResult::success(vec![Content::text(result)]))
+    }
 }
 
-#[tool(tool_box)]
 impl ServerHandler for PgMcp {
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut tools = Self::tool_box().list();
+        if let Some(role) = &self.role {
+            tools.retain(|tool| role.allows(&tool.name));
+        }
+        for tool in &mut tools {
+            add_intent_property(Arc::make_mut(&mut tool.input_schema));
+        }
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_tool_authorized(&request).map_err(map_pg_error)?;
+        let conn_id = request
+            .arguments
+            .as_ref()
+            .and_then(|args| args.get("conn_id"))
+            .and_then(|v| v.as_str());
+        self.check_rate_limit(conn_id).map_err(map_pg_error)?;
+        self.check_ddl_allowed(&request).map_err(map_pg_error)?;
+        let intent = call_intent(&request);
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %request.name,
+            intent = intent.as_deref()
+        );
+        if let Some(intent) = &intent {
+            tracing::info!(parent: &span, %intent, "tool call");
+        }
+        let context = ToolCallContext::new(self, request, context);
+        CALL_INTENT
+            .scope(intent, Self::tool_box().call(context))
+            .instrument(span)
+            .await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        if self.check_resources_authorized().is_err() {
+            return Ok(ListResourcesResult {
+                next_cursor: None,
+                resources: vec![],
+            });
+        }
+        self.check_rate_limit(None).map_err(map_pg_error)?;
+        let tables = self.conns.list_table_resources().await;
+        let resources = tables
+            .into_iter()
+            .map(|(conn_id, schema, table)| {
+                let mut resource = RawResource::new(
+                    table_resource_uri(&conn_id, &schema, &table),
+                    format!("{}.{}", schema, table),
+                );
+                resource.description = Some(format!(
+                    "Columns and sample rows of table {}.{} (connection {})",
+                    schema, table, conn_id
+                ));
+                resource.mime_type = Some("application/json".to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult {
+            next_cursor: None,
+            resources,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.check_resources_authorized().map_err(map_pg_error)?;
+        let (conn_id, schema, table) = parse_table_resource_uri(&request.uri).ok_or_else(|| {
+            McpError::resource_not_found(
+                format!(
+                    "Invalid resource URI: {}, expected pg://<conn_id>/<schema>/<table>",
+                    request.uri
+                ),
+                None,
+            )
+        })?;
+        self.check_rate_limit(Some(&conn_id))
+            .map_err(map_pg_error)?;
+        let text = self
+            .conns
+            .read_table_resource(&conn_id, &schema, &table)
+            .await
+            .map_err(map_pg_error)?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some("application/json".to_string()),
+                text,
+            }],
+        })
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult {
+            next_cursor: None,
+            prompts: prompts::list_prompts(),
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        prompts::get_prompt(&request.name, request.arguments.as_ref())
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some(
                 "A Postgres MCP server that allows AI agents to interact with Postgres databases"
                     .into(),
             ),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_prompts()
+                .build(),
             ..Default::default()
         }
     }
@@ -358,3 +2750,80 @@ impl Default for PgMcp {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ErrorCode;
+
+    #[test]
+    fn map_pg_error_should_report_policy_refusals_as_invalid_requests() {
+        let err = map_pg_error(PgMcpError::DdlDisabled("drop_table".to_string()));
+        assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(
+            err.data,
+            Some(serde_json::json!({"kind": "ddl_disabled", "tool": "drop_table"}))
+        );
+
+        let err = map_pg_error(PgMcpError::ToolNotAuthorized {
+            tool: "insert".to_string(),
+            role: "reader".to_string(),
+        });
+        assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(
+            err.data,
+            Some(serde_json::json!({
+                "kind": "not_authorized",
+                "tool": "insert",
+                "role": "reader",
+            }))
+        );
+
+        let err = map_pg_error(PgMcpError::RateLimited {
+            retry_after_ms: 250,
+        });
+        assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(
+            err.data,
+            Some(serde_json::json!({"kind": "rate_limited", "retry_after_ms": 250}))
+        );
+    }
+
+    #[test]
+    fn table_resource_uri_should_round_trip() {
+        let uri = table_resource_uri("conn-1", "my schema", "a/b%c");
+        assert_eq!(uri, "pg://conn-1/my%20schema/a%2Fb%25c");
+        assert_eq!(
+            parse_table_resource_uri(&uri),
+            Some((
+                "conn-1".to_string(),
+                "my schema".to_string(),
+                "a/b%c".to_string()
+            ))
+        );
+
+        assert_eq!(parse_table_resource_uri("pg://conn/public"), None);
+        assert_eq!(parse_table_resource_uri("pg://conn//table"), None);
+        assert_eq!(parse_table_resource_uri("pg://conn/a/b/c"), None);
+        assert_eq!(parse_table_resource_uri("pg://conn/public/%FF"), None);
+    }
+
+    #[test]
+    fn map_pg_error_should_report_validation_failures_as_invalid_params() {
+        let err = map_pg_error(PgMcpError::ValidationFailed {
+            kind: ValidationErrorKind::ReadOnly,
+            query: "DELETE FROM t".to_string(),
+            details: "connection is read-only".to_string(),
+        });
+        assert_eq!(err.code, ErrorCode::INVALID_PARAMS);
+        assert_eq!(
+            err.data,
+            Some(serde_json::json!({
+                "kind": "validation_failed",
+                "reason": "read_only",
+                "query": "DELETE FROM t",
+                "details": "connection is read-only",
+            }))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use rmcp::{
+    Error as McpError,
+    model::{
+        GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole,
+    },
+};
+
+/// A parameterized prompt guiding the model through a common database task.
+///
+/// `{name}` placeholders in the template are replaced by the prompt arguments.
+struct PromptTemplate {
+    name: &'static str,
+    description: &'static str,
+    // (name, description, required)
+    arguments: &'static [(&'static str, &'static str, bool)],
+    template: &'static str,
+}
+
+const CONN_ID_ARG: (&str, &str, bool) = (
+    "conn_id",
+    "Connection ID returned by the register tool",
+    true,
+);
+
+const PROMPTS: &[PromptTemplate] = &[
+    PromptTemplate {
+        name: "explore-schema",
+        description: "Explore the tables and columns of a schema",
+        arguments: &[
+            CONN_ID_ARG,
+            ("schema", "Schema to explore, defaults to public", false),
+        ],
+        template: "Explore the `{schema}` schema of the Postgres database registered as connection `{conn_id}`.
+
+1. Call `list_tables` with conn_id `{conn_id}` and schema `{schema}` to find the tables.
+2. Call `describe` for each table that looks relevant to learn its columns, types and defaults.
+3. If you need example data, call `query` with a `SELECT ... LIMIT 10`; never select unbounded rows.
+
+Finish with a short summary of each table, its purpose and how the tables relate to each other.",
+    },
+    PromptTemplate {
+        name: "safe-migration",
+        description: "Plan and apply a schema change without losing data",
+        arguments: &[
+            CONN_ID_ARG,
+            ("change", "Description of the schema change to make", true),
+        ],
+        template: "Apply the following schema change to the Postgres database registered as connection `{conn_id}`:
+
+{change}
+
+1. Call `list_tables` and `describe` to inspect the current schema of every table the change touches.
+2. Write out the exact SQL statements you plan to run and explain their effect on existing data. Ask for confirmation before running anything destructive (dropping tables, columns or indexes).
+3. Apply the change one statement at a time with the matching tool (`create_table`, `create_index`, `create_type`, ...).
+4. Call `describe` again to verify the result.",
+    },
+    PromptTemplate {
+        name: "write-analytics-query",
+        description: "Answer an analytics question with a read-only SQL query",
+        arguments: &[
+            CONN_ID_ARG,
+            ("question", "The question to answer from the data", true),
+            ("schema", "Schema holding the data, defaults to public", false),
+        ],
+        template: "Answer the following question using the Postgres database registered as connection `{conn_id}`:
+
+{question}
+
+1. Call `list_tables` with schema `{schema}` and `describe` the relevant tables before writing any SQL.
+2. Write a single SELECT statement (CTEs are fine) that answers the question. Aggregate in SQL rather than fetching raw rows, and add a LIMIT when returning rows.
+3. Run it with the `query` tool, fix any errors, and explain the result in plain language together with the SQL you used.",
+    },
+];
+
+const DEFAULT_SCHEMA: &str = "public";
+
+pub(crate) fn list_prompts() -> Vec<Prompt> {
+    PROMPTS
+        .iter()
+        .map(|p| {
+            let arguments = p
+                .arguments
+                .iter()
+                .map(|(name, description, required)| PromptArgument {
+                    name: name.to_string(),
+                    description: Some(description.to_string()),
+                    required: Some(*required),
+                })
+                .collect();
+            Prompt::new(p.name, Some(p.description), Some(arguments))
+        })
+        .collect()
+}
+
+pub(crate) fn get_prompt(
+    name: &str,
+    arguments: Option<&JsonObject>,
+) -> Result<GetPromptResult, McpError> {
+    let prompt = PROMPTS
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| McpError::invalid_params(format!("Prompt not found: {}", name), None))?;
+
+    let mut text = prompt.template.to_string();
+    for (arg, _, required) in prompt.arguments {
+        let value = arguments
+            .and_then(|args| args.get(*arg))
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty());
+        let value = match value {
+            Some(value) => value,
+            None if *arg == "schema" => DEFAULT_SCHEMA,
+            None if *required => {
+                return Err(McpError::invalid_params(
+                    format!("Missing required argument '{}' for prompt {}", arg, name),
+                    None,
+                ));
+            }
+            None => "",
+        };
+        text = text.replace(&format!("{{{}}}", arg), value);
+    }
+
+    Ok(GetPromptResult {
+        description: Some(prompt.description.to_string()),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::PromptMessageContent;
+
+    #[test]
+    fn get_prompt_should_fill_in_arguments() {
+        let args = rmcp::object!({ "conn_id": "abc", "question": "How many users?" });
+        let result = get_prompt("write-analytics-query", Some(&args)).unwrap();
+        let PromptMessageContent::Text { text } = &result.messages[0].content else {
+            panic!("expected text content");
+        };
+        assert!(text.contains("connection `abc`"));
+        assert!(text.contains("How many users?"));
+        assert!(text.contains("schema `public`"));
+        assert!(!text.contains('{'));
+    }
+
+    #[test]
+    fn get_prompt_should_reject_missing_arguments() {
+        let args = rmcp::object!({ "conn_id": "abc" });
+        assert!(get_prompt("safe-migration", Some(&args)).is_err());
+        assert!(get_prompt("no-such-prompt", Some(&args)).is_err());
+        assert_eq!(list_prompts().len(), PROMPTS.len());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A token-bucket rate limiter keyed by an arbitrary string (e.g. a conn_id).
+///
+/// Each key gets its own bucket holding up to `rate` tokens which refills at
+/// `rate` tokens per second, so short bursts up to one second's worth of
+/// requests are allowed.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_sec: u32) -> Self {
+        Self {
+            rate: requests_per_sec.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// An empty limiter with the same rate, for buckets that must not be
+    /// shared with this one's.
+    pub(crate) fn for_new_session(&self) -> Self {
+        Self {
+            rate: self.rate,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Drop the bucket of `key`, e.g. when its connection is unregistered.
+    pub(crate) fn remove(&self, key: &str) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.remove(key);
+    }
+
+    /// Take a token for `key`. Returns how long to wait before retrying if the
+    /// bucket is empty.
+    pub(crate) fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.rate,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_should_reject_when_bucket_is_empty() {
+        let limiter = RateLimiter::new(2);
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_ok());
+        let retry_after = limiter.check_at("a", now).unwrap_err();
+        assert!(retry_after > Duration::ZERO);
+        assert!(retry_after <= Duration::from_millis(500));
+
+        // other keys have their own bucket
+        assert!(limiter.check_at("b", now).is_ok());
+
+        // tokens refill over time
+        assert!(limiter.check_at("a", now + retry_after).is_ok());
+
+        // a removed key starts over with a full bucket
+        limiter.remove("a");
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_ok());
+
+        // a limiter for another session doesn't share buckets
+        let other = limiter.for_new_session();
+        assert!(other.check_at("b", now).is_ok());
+        assert!(other.check_at("b", now).is_ok());
+        assert!(other.check_at("b", now).is_err());
+    }
+}
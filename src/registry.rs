@@ -0,0 +1,60 @@
+//! The registry file of --registry-file, which keeps the registered
+//! connections across restarts.
+//!
+//! It holds the connections in the format of export_connections, but with
+//! their passwords, so it is created readable by its owner only.
+
+use crate::pg::ExportedConnection;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub(crate) struct Registry {
+    path: PathBuf,
+    /// Serializes saves, so the last one written is the latest state.
+    lock: Mutex<()>,
+}
+
+impl Registry {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The saved connections; none if the file doesn't exist yet.
+    pub(crate) fn load(&self) -> io::Result<Vec<ExportedConnection>> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the saved connections with the ones `connections` returns,
+    /// called under the lock so concurrent saves can't reorder. The file is
+    /// written next to the registry and renamed over it, so a crash never
+    /// leaves it half written.
+    pub(crate) fn save(
+        &self,
+        connections: impl FnOnce() -> Vec<ExportedConnection>,
+    ) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let json = serde_json::to_string_pretty(&connections())?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
@@ -0,0 +1,254 @@
+//! SSE transport for the MCP server.
+//!
+//! This follows rmcp's `transport::sse_server`, which has no way to configure
+//! keep-alives: proxies that drop idle connections would otherwise close the
+//! event stream between tool calls. It also has no authentication, which
+//! this transport adds with bearer tokens mapped to tool roles.
+
+use crate::{AuthConfig, ToolRole};
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post},
+};
+use futures::SinkExt;
+use rmcp::{
+    RoleServer, Service, ServiceExt,
+    model::ClientJsonRpcMessage,
+    service::{RxJsonRpcMessage, TxJsonRpcMessage},
+};
+use std::{
+    collections::HashMap, convert::Infallible, io, net::SocketAddr, sync::Arc, time::Duration,
+};
+use tokio::sync::{RwLock, mpsc};
+use tokio_stream::{Stream, StreamExt, wrappers::ReceiverStream};
+use tokio_util::sync::{CancellationToken, PollSender};
+use tracing::Instrument;
+
+type SessionId = Arc<str>;
+type TxStore = Arc<RwLock<HashMap<SessionId, Session>>>;
+
+#[derive(Debug, Clone)]
+struct Session {
+    tx: mpsc::Sender<ClientJsonRpcMessage>,
+    /// Name of the role the session authenticated as.
+    role: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SseServerConfig {
+    pub bind: SocketAddr,
+    pub sse_path: String,
+    pub post_path: String,
+    /// Interval between keep-alive comments on idle event streams. `None`
+    /// disables keep-alives.
+    pub keep_alive: Option<Duration>,
+    /// Bearer tokens accepted on both endpoints and the tools each may call.
+    /// `None` accepts every client.
+    pub auth: Option<Arc<AuthConfig>>,
+    pub ct: CancellationToken,
+}
+
+/// A transport for one SSE session: messages POSTed by the client come in on
+/// `stream`, messages sent to `sink` go out as `message` events.
+pub struct SseServerTransport {
+    session_id: SessionId,
+    role: Option<ToolRole>,
+    stream: ReceiverStream<RxJsonRpcMessage<RoleServer>>,
+    sink: PollSender<TxJsonRpcMessage<RoleServer>>,
+}
+
+#[derive(Clone)]
+struct App {
+    txs: TxStore,
+    transport_tx: mpsc::UnboundedSender<SseServerTransport>,
+    post_path: Arc<str>,
+    keep_alive: Option<Duration>,
+    auth: Option<Arc<AuthConfig>>,
+}
+
+/// The role of the request's bearer token. Without an auth config every
+/// request is let in without a role.
+pub(crate) fn bearer_role(
+    auth: Option<&AuthConfig>,
+    headers: &HeaderMap,
+) -> Result<Option<ToolRole>, StatusCode> {
+    let Some(auth) = auth else {
+        return Ok(None);
+    };
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| auth.role(token.trim()))
+        .map(Some)
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostEventQuery {
+    session_id: String,
+}
+
+async fn post_event_handler(
+    State(app): State<App>,
+    Query(PostEventQuery { session_id }): Query<PostEventQuery>,
+    headers: HeaderMap,
+    Json(message): Json<ClientJsonRpcMessage>,
+) -> Result<StatusCode, StatusCode> {
+    tracing::debug!(session_id, ?message, "new client message");
+    let role = bearer_role(app.auth.as_deref(), &headers)?;
+    let session = app
+        .txs
+        .read()
+        .await
+        .get(session_id.as_str())
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // A token of another role can't post into the session.
+    if role.map(|role| role.name) != session.role {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if session.tx.send(message).await.is_err() {
+        tracing::error!("send message error");
+        return Err(StatusCode::GONE);
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn sse_handler(
+    State(app): State<App>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let role = bearer_role(app.auth.as_deref(), &headers)?;
+    let session: SessionId = uuid::Uuid::new_v4().simple().to_string().into();
+    tracing::info!(%session, role = role.as_ref().map(|role| role.name.as_str()), "sse connection");
+
+    let (from_client_tx, from_client_rx) = mpsc::channel(64);
+    let (to_client_tx, to_client_rx) = mpsc::channel(64);
+    app.txs.write().await.insert(
+        session.clone(),
+        Session {
+            tx: from_client_tx,
+            role: role.as_ref().map(|role| role.name.clone()),
+        },
+    );
+
+    let transport = SseServerTransport {
+        session_id: session.clone(),
+        role,
+        stream: ReceiverStream::new(from_client_rx),
+        sink: PollSender::new(to_client_tx),
+    };
+    if app.transport_tx.send(transport).is_err() {
+        tracing::warn!("server is closed, dropping sse connection");
+        app.txs.write().await.remove(&session);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("{}?sessionId={}", app.post_path, session));
+    let messages = ReceiverStream::new(to_client_rx).filter_map(|message| {
+        match serde_json::to_string(&message) {
+            Ok(data) => Some(Event::default().event("message").data(data)),
+            Err(e) => {
+                tracing::error!(error = %e, "failed to serialize message");
+                None
+            }
+        }
+    });
+    let stream = tokio_stream::once(endpoint).chain(messages).map(Ok);
+
+    let sse = Sse::new(stream);
+    Ok(match app.keep_alive {
+        Some(interval) => sse.keep_alive(KeepAlive::new().interval(interval)),
+        None => sse,
+    })
+}
+
+#[derive(Debug)]
+pub struct SseServer {
+    transport_rx: mpsc::UnboundedReceiver<SseServerTransport>,
+    txs: TxStore,
+    pub config: SseServerConfig,
+}
+
+impl SseServer {
+    pub async fn serve_with_config(config: SseServerConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = mpsc::unbounded_channel();
+        let txs = TxStore::default();
+        let app = App {
+            txs: txs.clone(),
+            transport_tx,
+            post_path: config.post_path.as_str().into(),
+            keep_alive: config.keep_alive,
+            auth: config.auth.clone(),
+        };
+
+        let listener = tokio::net::TcpListener::bind(config.bind).await?;
+        let router = Router::new()
+            .route(&config.sse_path, get(sse_handler))
+            .route(&config.post_path, post(post_event_handler))
+            .with_state(app);
+        let ct = config.ct.child_token();
+        let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+            ct.cancelled().await;
+            tracing::info!("sse server cancelled");
+        });
+        tokio::spawn(
+            async move {
+                if let Err(e) = server.await {
+                    tracing::error!(error = %e, "sse server shutdown with error");
+                }
+            }
+            .instrument(tracing::info_span!("sse-server", bind_address = %config.bind)),
+        );
+
+        Ok(Self {
+            transport_rx,
+            txs,
+            config,
+        })
+    }
+
+    /// Serve every new SSE session with a service from `service_provider`,
+    /// given the role the session authenticated as. Returns the token that
+    /// shuts the sessions down.
+    pub fn with_service<S, F>(mut self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn(Option<ToolRole>) -> S + Send + 'static,
+    {
+        let ct = self.config.ct.clone();
+        tokio::spawn(async move {
+            while let Some(mut transport) = self.transport_rx.recv().await {
+                let service = service_provider(transport.role.take());
+                let ct = self.config.ct.child_token();
+                let txs = self.txs.clone();
+                tokio::spawn(async move {
+                    let SseServerTransport {
+                        session_id,
+                        stream,
+                        sink,
+                        ..
+                    } = transport;
+                    let sink = sink.sink_map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e));
+                    match service.serve_with_ct((sink, stream), ct).await {
+                        Ok(server) => {
+                            if let Err(e) = server.waiting().await {
+                                tracing::error!(%session_id, error = %e, "sse session failed");
+                            }
+                        }
+                        Err(e) => tracing::error!(%session_id, error = %e, "sse session failed"),
+                    }
+                    txs.write().await.remove(&session_id);
+                });
+            }
+        });
+        ct
+    }
+}
@@ -0,0 +1,238 @@
+use serde::Deserialize;
+
+/// A column of a table to fill with generated values, as introspected by
+/// `Conns::generate_test_data`.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DataColumn {
+    pub(crate) name: String,
+    /// pg_type name of the column, or of the base type of a domain.
+    pub(crate) type_name: String,
+    /// `format_type` of the column, which values are cast to.
+    pub(crate) sql_type: String,
+    pub(crate) not_null: bool,
+    pub(crate) has_default: bool,
+    /// Identity, generated or serial: Postgres fills it.
+    pub(crate) generated: bool,
+    pub(crate) max_length: Option<usize>,
+    pub(crate) numeric_precision: Option<u32>,
+    pub(crate) numeric_scale: Option<u32>,
+    pub(crate) enum_values: Option<Vec<String>>,
+    /// Table and column referenced by a single-column foreign key.
+    pub(crate) references: Option<(String, String)>,
+}
+
+/// How the values of a column are produced.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ValueKind {
+    Integer {
+        max: u64,
+    },
+    Numeric {
+        int_digits: u32,
+        scale: u32,
+    },
+    Float,
+    Bool,
+    Text {
+        max_length: Option<usize>,
+        email: bool,
+    },
+    Uuid,
+    /// Seconds after 2024-01-01 00:00:00 UTC, within a year.
+    Timestamp,
+    /// Seconds after midnight.
+    Time,
+    Interval,
+    Json,
+    Bytea,
+    OneOf(Vec<String>),
+}
+
+impl ValueKind {
+    /// The kind of values for a column, `None` if its type isn't supported.
+    /// Foreign keys are resolved separately, to values of the referenced
+    /// column.
+    pub(crate) fn for_column(column: &DataColumn) -> Option<Self> {
+        if let Some(values) = &column.enum_values {
+            return Some(Self::OneOf(values.clone()));
+        }
+        let kind = match column.type_name.as_str() {
+            "int2" => Self::Integer { max: 32_767 },
+            "int4" | "int8" | "oid" => Self::Integer { max: 1_000_000 },
+            "numeric" => {
+                let scale = column.numeric_scale.unwrap_or(2);
+                let int_digits = column
+                    .numeric_precision
+                    .map_or(6, |precision| precision.saturating_sub(scale).min(6));
+                Self::Numeric { int_digits, scale }
+            }
+            "float4" | "float8" => Self::Float,
+            "bool" => Self::Bool,
+            "text" | "varchar" | "bpchar" | "name" | "citext" => Self::Text {
+                max_length: column.max_length,
+                email: column.name.to_lowercase().contains("email"),
+            },
+            "uuid" => Self::Uuid,
+            "date" | "timestamp" | "timestamptz" => Self::Timestamp,
+            "time" | "timetz" => Self::Time,
+            "interval" => Self::Interval,
+            "json" | "jsonb" => Self::Json,
+            "bytea" => Self::Bytea,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// SQL turning the text parameter `value` into the column's type.
+    pub(crate) fn cast(&self, value: &str, sql_type: &str) -> String {
+        match self {
+            Self::Timestamp => format!(
+                "(timestamptz '2024-01-01 00:00:00+00' + {}::bigint * interval '1 second')::{}",
+                value, sql_type
+            ),
+            Self::Time => format!(
+                "(time '00:00' + {}::bigint * interval '1 second')::{}",
+                value, sql_type
+            ),
+            _ => format!("{}::{}", value, sql_type),
+        }
+    }
+
+    pub(crate) fn generate(&self, rng: &mut SplitMix64) -> String {
+        match self {
+            Self::Integer { max } => rng.below(*max + 1).to_string(),
+            Self::Numeric { int_digits, scale } => {
+                let int_part = rng.below(10u64.pow(*int_digits));
+                if *scale == 0 {
+                    int_part.to_string()
+                } else {
+                    let scale = (*scale).min(6);
+                    let fraction = rng.below(10u64.pow(scale));
+                    format!("{}.{:0width$}", int_part, fraction, width = scale as usize)
+                }
+            }
+            Self::Float => format!("{:.4}", rng.unit() * 1000.0),
+            Self::Bool => (rng.below(2) == 1).to_string(),
+            Self::Text { max_length, email } => {
+                let text = if *email {
+                    format!("{}{}@example.com", rng.pick(WORDS), rng.below(100_000))
+                } else {
+                    let words = 1 + rng.below(4) as usize;
+                    (0..words)
+                        .map(|_| rng.pick(WORDS))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                match max_length {
+                    Some(max) => text.chars().take(*max).collect(),
+                    None => text,
+                }
+            }
+            Self::Uuid => {
+                let (high, low) = (rng.next_u64(), rng.next_u64());
+                // version 4, RFC 4122 variant
+                let high = (high & !0xf000) | 0x4000;
+                let low = (low & !(0b11 << 62)) | (0b10 << 62);
+                format!(
+                    "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                    high >> 32,
+                    (high >> 16) & 0xffff,
+                    high & 0xffff,
+                    low >> 48,
+                    low & 0xffff_ffff_ffff
+                )
+            }
+            Self::Timestamp => rng.below(365 * 86_400).to_string(),
+            Self::Time => rng.below(86_400).to_string(),
+            Self::Interval => format!("{} minutes", rng.below(10_000)),
+            Self::Json => format!(
+                r#"{{"value": {}, "label": "{}"}}"#,
+                rng.below(1000),
+                rng.pick(WORDS)
+            ),
+            Self::Bytea => {
+                let bytes = rng.next_u64();
+                format!("\\x{:016x}", bytes)
+            }
+            Self::OneOf(values) => rng.pick(values).to_string(),
+        }
+    }
+}
+
+const WORDS: &[&str] = &[
+    "alpha", "bravo", "cedar", "delta", "ember", "falcon", "garnet", "harbor", "indigo", "juniper",
+    "kestrel", "lumen", "maple", "nova", "onyx", "pioneer", "quartz", "raven", "sierra", "timber",
+    "umber", "violet", "willow", "xenon", "yarrow", "zephyr",
+];
+
+/// SplitMix64: a small, seedable generator whose sequence for a seed never
+/// changes, which is all reproducible test data needs.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, `n` > 0.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A number in `[0, 1)`.
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn pick<'a, T: AsRef<str>>(&mut self, values: &'a [T]) -> &'a str {
+        values[self.below(values.len() as u64) as usize].as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_should_generate_same_values() {
+        let kinds = [
+            ValueKind::Integer { max: 100 },
+            ValueKind::Text {
+                max_length: Some(5),
+                email: false,
+            },
+            ValueKind::Uuid,
+            ValueKind::Numeric {
+                int_digits: 3,
+                scale: 2,
+            },
+        ];
+        let generate = |seed| {
+            let mut rng = SplitMix64::new(seed);
+            kinds
+                .iter()
+                .map(|kind| kind.generate(&mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+
+        let values = generate(7);
+        assert!(values[0].parse::<u64>().unwrap() <= 100);
+        assert!(values[1].chars().count() <= 5);
+        assert_eq!(values[2].len(), 36);
+        assert_eq!(&values[2][14..15], "4");
+        let (int_part, fraction) = values[3].split_once('.').unwrap();
+        assert!(int_part.len() <= 3);
+        assert_eq!(fraction.len(), 2);
+    }
+}
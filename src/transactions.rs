@@ -0,0 +1,212 @@
+//! Transactions opened with `begin_transaction` that span several tool calls.
+//!
+//! Each one holds a connection taken out of its pool until it is committed or
+//! rolled back, so a client that disappears mid-transaction would leak the
+//! connection and its locks. A background task rolls back transactions left
+//! idle for longer than the idle timeout.
+
+use crate::pg::PgMcpError;
+use sqlx::postgres::PgConnection;
+use sqlx::{Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::MappedMutexGuard;
+
+/// How long a transaction may stay idle before it is rolled back, unless
+/// configured with --tx-idle-timeout-secs.
+pub(crate) const DEFAULT_TX_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+type OpenTransactions = Mutex<HashMap<String, Arc<OpenTransaction>>>;
+
+#[derive(Debug)]
+pub(crate) struct OpenTransaction {
+    conn_id: String,
+    /// `None` once the transaction has ended.
+    tx: tokio::sync::Mutex<Option<Transaction<'static, Postgres>>>,
+    last_used: Mutex<Instant>,
+    /// A statement failed, so Postgres aborted the transaction and a COMMIT
+    /// would silently roll it back.
+    failed: AtomicBool,
+}
+
+/// The open transaction of a tool call, locked so calls on the same
+/// transaction run one at a time. Dropping it marks the transaction used.
+pub(crate) struct TxGuard<'a> {
+    tx: MappedMutexGuard<'a, Transaction<'static, Postgres>>,
+    open: &'a OpenTransaction,
+}
+
+impl TxGuard<'_> {
+    pub(crate) fn conn(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    /// Pass a statement's result through, remembering a failure.
+    pub(crate) fn check<T>(&self, result: Result<T, sqlx::Error>) -> Result<T, sqlx::Error> {
+        if result.is_err() {
+            self.open.failed.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl Drop for TxGuard<'_> {
+    fn drop(&mut self) {
+        *self
+            .open
+            .last_used
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+}
+
+impl OpenTransaction {
+    /// Lock the transaction for a statement. Fails if it ended while waiting.
+    pub(crate) async fn lock(&self, tx_id: &str) -> Result<TxGuard<'_>, PgMcpError> {
+        let tx = tokio::sync::MutexGuard::try_map(self.tx.lock().await, Option::as_mut)
+            .map_err(|_| PgMcpError::TransactionNotFound(tx_id.to_string()))?;
+        Ok(TxGuard { tx, open: self })
+    }
+}
+
+/// The open transactions of all connections, keyed by tx_id.
+#[derive(Debug, Clone)]
+pub(crate) struct Transactions {
+    open: Arc<OpenTransactions>,
+    pub(crate) idle_timeout: Duration,
+    reaper_started: Arc<AtomicBool>,
+}
+
+impl Transactions {
+    pub(crate) fn new(idle_timeout: Duration) -> Self {
+        Self {
+            open: Arc::default(),
+            idle_timeout,
+            reaper_started: Arc::default(),
+        }
+    }
+
+    /// Keep `tx` open for later calls on connection `conn_id`. Returns its
+    /// tx_id.
+    pub(crate) fn insert(&self, conn_id: &str, tx: Transaction<'static, Postgres>) -> String {
+        let tx_id = uuid::Uuid::new_v4().to_string();
+        let open = OpenTransaction {
+            conn_id: conn_id.to_string(),
+            tx: tokio::sync::Mutex::new(Some(tx)),
+            last_used: Mutex::new(Instant::now()),
+            failed: AtomicBool::new(false),
+        };
+        self.lock_open().insert(tx_id.clone(), Arc::new(open));
+        if !self.reaper_started.swap(true, Ordering::Relaxed) {
+            tokio::spawn(reap_idle(Arc::downgrade(&self.open), self.idle_timeout));
+        }
+        tx_id
+    }
+
+    /// The open transaction `tx_id` of connection `conn_id`.
+    pub(crate) fn get(
+        &self,
+        tx_id: &str,
+        conn_id: &str,
+    ) -> Result<Arc<OpenTransaction>, PgMcpError> {
+        self.lock_open()
+            .get(tx_id)
+            .filter(|open| open.conn_id == conn_id)
+            .cloned()
+            .ok_or_else(|| PgMcpError::TransactionNotFound(tx_id.to_string()))
+    }
+
+    /// End the open transaction `tx_id` of connection `conn_id`, committing
+    /// it if `commit` is set. A transaction aborted by a failed statement is
+    /// rolled back and reported as an error, which a COMMIT would hide.
+    pub(crate) async fn end(
+        &self,
+        tx_id: &str,
+        conn_id: &str,
+        commit: bool,
+    ) -> Result<(), PgMcpError> {
+        let open = self.get(tx_id, conn_id)?;
+        self.lock_open().remove(tx_id);
+        let tx = open
+            .tx
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| PgMcpError::TransactionNotFound(tx_id.to_string()))?;
+        let operation = if commit {
+            "commit_transaction"
+        } else {
+            "rollback_transaction"
+        };
+        let db_err = |e: sqlx::Error| PgMcpError::DatabaseError {
+            operation: operation.to_string(),
+            underlying: e.to_string(),
+        };
+        if !commit || open.failed.load(Ordering::Relaxed) {
+            tx.rollback().await.map_err(db_err)?;
+        } else {
+            tx.commit().await.map_err(db_err)?;
+        }
+        if commit && open.failed.load(Ordering::Relaxed) {
+            return Err(PgMcpError::DatabaseError {
+                operation: operation.to_string(),
+                underlying: "a statement of the transaction failed, so it was rolled back"
+                    .to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Drop the open transactions of an unregistered connection, which rolls
+    /// them back.
+    pub(crate) fn remove_conn(&self, conn_id: &str) {
+        self.lock_open().retain(|_, open| open.conn_id != conn_id);
+    }
+
+    fn lock_open(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<OpenTransaction>>> {
+        self.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Roll back transactions idle for longer than `idle_timeout`, until the
+// transactions are dropped with their `Conns`. A transaction running a
+// statement is never idle.
+async fn reap_idle(open: Weak<OpenTransactions>, idle_timeout: Duration) {
+    let interval = (idle_timeout / 4).clamp(Duration::from_millis(100), Duration::from_secs(30));
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(open) = open.upgrade() else {
+            return;
+        };
+        let idle: Vec<(String, Arc<OpenTransaction>)> = {
+            let mut open = open.lock().unwrap_or_else(|e| e.into_inner());
+            let idle_ids: Vec<String> = open
+                .iter()
+                .filter(|(_, tx)| {
+                    let last_used = *tx.last_used.lock().unwrap_or_else(|e| e.into_inner());
+                    last_used.elapsed() > idle_timeout && tx.tx.try_lock().is_ok()
+                })
+                .map(|(tx_id, _)| tx_id.clone())
+                .collect();
+            idle_ids
+                .into_iter()
+                .filter_map(|tx_id| open.remove(&tx_id).map(|tx| (tx_id, tx)))
+                .collect()
+        };
+        for (tx_id, tx) in idle {
+            let Some(transaction) = tx.tx.lock().await.take() else {
+                continue;
+            };
+            let result = transaction.rollback().await;
+            tracing::warn!(
+                %tx_id,
+                conn_id = %tx.conn_id,
+                idle_timeout_secs = idle_timeout.as_secs_f64(),
+                error = result.err().map(|e| e.to_string()),
+                "rolled back idle transaction"
+            );
+        }
+    }
+}